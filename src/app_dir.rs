@@ -1,39 +1,282 @@
-use std::{path::{PathBuf, Path}, fmt::{self, Display}, error::Error};
+use std::{path::{PathBuf, Path}, fmt::{self, Display}, error::Error, fs, io, env};
 use directories::{ProjectDirs, BaseDirs, UserDirs};
 use std::sync::Mutex;
+#[cfg(all(target_family = "unix", feature = "file-mode"))]
+use file_mode::Mode;
 
-struct AppInfo {
+/// Application identity used to locate application directories and for display (e.g. log headers, user agents).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppInfo {
     pub name: String,
     pub author: String,
+    pub version: Option<String>,
+    /// Reverse domain name qualifier (e.g. `com`); used by project directories on macOS.
+    pub qualifier: String,
+    /// Organization name; author is used if not set.
+    pub organization: Option<String>,
+}
+
+impl AppInfo {
+    /// Constructs application info with given name and author.
+    pub fn new(name: impl Into<String>, author: impl Into<String>) -> AppInfo {
+        AppInfo {
+            name: name.into(),
+            author: author.into(),
+            version: None,
+            qualifier: String::new(),
+            organization: None,
+        }
+    }
+
+    /// Constructs application info from name, author and version of clap command.
+    ///
+    /// Author and version are only available if set in the command (e.g. with `#[command(author, version)]`).
+    #[cfg(feature = "clap")]
+    pub fn from_command(command: &clap::Command) -> AppInfo {
+        AppInfo {
+            version: command.get_version().map(ToOwned::to_owned),
+            ..AppInfo::new(command.get_name(), command.get_author().unwrap_or("Anonymous"))
+        }
+    }
+
+    /// Sets application version.
+    pub fn with_version(mut self, version: impl Into<String>) -> AppInfo {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Sets reverse domain name qualifier.
+    pub fn with_qualifier(mut self, qualifier: impl Into<String>) -> AppInfo {
+        self.qualifier = qualifier.into();
+        self
+    }
+
+    /// Sets organization name.
+    pub fn with_organization(mut self, organization: impl Into<String>) -> AppInfo {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    /// Returns organization name or author if not set.
+    pub fn organization(&self) -> &str {
+        self.organization.as_deref().unwrap_or(&self.author)
+    }
+
+    /// Returns project directories of the application.
+    pub fn project_dirs(&self) -> Result<ProjectDirs, AppDirError> {
+        ProjectDirs::from(&self.qualifier, self.organization(), &self.name).ok_or(AppDirError::NoProjectDir)
+    }
+
+    /// Returns user agent string like `name/version`.
+    pub fn user_agent(&self) -> String {
+        match self.version.as_ref() {
+            Some(version) => format!("{}/{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl<N: Into<String>, A: Into<String>> From<(N, A)> for AppInfo {
+    fn from((name, author): (N, A)) -> AppInfo {
+        AppInfo::new(name, author)
+    }
+}
+
+impl Display for AppInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version.as_ref() {
+            Some(version) => write!(f, "{} {}", self.name, version),
+            None => write!(f, "{}", self.name),
+        }
+    }
 }
 
 static APP_INFO: Mutex<Option<AppInfo>> = Mutex::new(None);
 
-/// Initializes application name and author with CARGO_PKG_NAME and CARGO_PKG_AUTHORS.
+#[cfg(all(target_family = "unix", feature = "file-mode"))]
+static APP_DIR_MODE: Mutex<Option<Mode>> = Mutex::new(None);
+
+struct AppDirOverrides {
+    root: Option<PathBuf>,
+    dirs: Vec<(AppDirKind, PathBuf)>,
+    portable: bool,
+}
+
+static APP_DIR_OVERRIDES: Mutex<AppDirOverrides> = Mutex::new(AppDirOverrides { root: None, dirs: Vec::new(), portable: false });
+
+/// Initializes application name, author and version with CARGO_PKG_NAME, CARGO_PKG_AUTHORS and CARGO_PKG_VERSION.
 #[macro_export]
 macro_rules! init_app_info {
     () => {
-        init_app_info_with(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_AUTHORS"))
+        init_app_info_from(AppInfo::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_AUTHORS")).with_version(env!("CARGO_PKG_VERSION")))
     };
 }
 
 pub use init_app_info;
 
-/// Initializes application name and author with given values.
-pub fn init_app_info_with(name: impl Into<String>, author: impl Into<String>) {
+/// Initializes application info with given value.
+///
+/// Application info can only be initialized once; subsequent calls have no effect.
+pub fn init_app_info_from(info: AppInfo) {
     let mut app_info = APP_INFO.lock().unwrap();
     if app_info.is_none() {
-        app_info.replace(AppInfo { name: name.into(), author: author.into() });
+        app_info.replace(info);
     }
 }
 
+/// Initializes application name and author with given values.
+pub fn init_app_info_with(name: impl Into<String>, author: impl Into<String>) {
+    init_app_info_from(AppInfo::new(name, author))
+}
+
+/// Initializes application info from name, author and version of clap parser command.
+#[cfg(feature = "clap")]
+pub fn init_app_info_from_parser<P: clap::CommandFactory>() {
+    init_app_info_from(AppInfo::from_command(&P::command()))
+}
+
 /// Initializes application name and author guessing from environment.
 pub fn init_app_info_guess() {
     let mut app_info = APP_INFO.lock().unwrap();
     if app_info.is_none() {
         let name = std::env::args().next().and_then(|a| Path::new(&a).file_name().and_then(|n| n.to_str().map(ToOwned::to_owned)));
-        app_info.replace(AppInfo { name: name.unwrap_or("cotton".to_owned()), author: "Anonymous".to_owned() });
+        app_info.replace(AppInfo::new(name.unwrap_or("cotton".to_owned()), "Anonymous"));
+    }
+}
+
+/// Returns application info, guessing it from environment if not initialized.
+pub fn app_info() -> AppInfo {
+    init_app_info_guess();
+    APP_INFO.lock().unwrap().as_ref().unwrap().clone()
+}
+
+fn app_name() -> String {
+    app_info().name
+}
+
+/// Kind of application directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppDirKind {
+    Data,
+    Cache,
+    Config,
+    State,
+    Runtime,
+    Log,
+}
+
+impl AppDirKind {
+    /// Name of the directory used when application directories are located under common root directory.
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            AppDirKind::Data => "data",
+            AppDirKind::Cache => "cache",
+            AppDirKind::Config => "config",
+            AppDirKind::State => "state",
+            AppDirKind::Runtime => "run",
+            AppDirKind::Log => "log",
+        }
+    }
+
+    /// Name of environment variable that overrides location of this directory (e.g. `MY_APP_DATA_DIR`).
+    pub fn env_var(&self) -> String {
+        let kind = match self {
+            AppDirKind::Data => "DATA",
+            AppDirKind::Cache => "CACHE",
+            AppDirKind::Config => "CONFIG",
+            AppDirKind::State => "STATE",
+            AppDirKind::Runtime => "RUNTIME",
+            AppDirKind::Log => "LOG",
+        };
+        format!("{}_{}_DIR", app_env_prefix(), kind)
+    }
+
+    fn resolve(&self) -> Result<PathBuf, AppDirError> {
+        if let Some(dir) = self.override_dir()? {
+            return Ok(dir)
+        }
+
+        match self {
+            AppDirKind::Log => Ok(AppDirKind::State.resolve()?.join("log")),
+            _ => self.project_dir(&project_dirs()?),
+        }
+    }
+
+    fn project_dir(&self, project_dirs: &ProjectDirs) -> Result<PathBuf, AppDirError> {
+        Ok(match self {
+            AppDirKind::Data => project_dirs.data_dir().to_owned(),
+            AppDirKind::Cache => project_dirs.cache_dir().to_owned(),
+            AppDirKind::Config => project_dirs.config_dir().to_owned(),
+            AppDirKind::State => project_dirs.state_dir().unwrap_or_else(|| project_dirs.data_local_dir()).to_owned(),
+            AppDirKind::Runtime => project_dirs.runtime_dir().ok_or(AppDirError::NoRuntimeDir)?.to_owned(),
+            AppDirKind::Log => AppDirKind::State.project_dir(project_dirs)?.join("log"),
+        })
     }
+
+    fn override_dir(&self) -> Result<Option<PathBuf>, AppDirError> {
+        let overrides = APP_DIR_OVERRIDES.lock().unwrap();
+
+        if let Some((_, dir)) = overrides.dirs.iter().find(|(kind, _)| kind == self) {
+            return Ok(Some(dir.clone()))
+        }
+
+        if let Some(root) = overrides.root.as_ref() {
+            return Ok(Some(root.join(self.dir_name())))
+        }
+
+        if let Some(dir) = env::var_os(self.env_var()).filter(|dir| !dir.is_empty()) {
+            return Ok(Some(dir.into()))
+        }
+
+        if overrides.portable || env::var_os(format!("{}_PORTABLE", app_env_prefix())).filter(|v| !v.is_empty()).is_some() {
+            let exe = env::current_exe().map_err(AppDirError::NoExecutableDir)?;
+            let exe_dir = exe.parent().ok_or_else(|| AppDirError::NoExecutableDir(io::Error::new(io::ErrorKind::NotFound, "executable has no parent directory")))?;
+            return Ok(Some(exe_dir.join(self.dir_name())))
+        }
+
+        Ok(None)
+    }
+}
+
+/// Prefix of environment variables used to override application directories; upper case application name with
+/// non-alphanumeric characters replaced by `_`.
+fn app_env_prefix() -> String {
+    app_name().chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+/// Sets root directory under which all application directories are located (e.g. `<root>/data`, `<root>/cache`).
+///
+/// This takes precedence over environment variables and portable mode and is useful to sandbox tests.
+/// Pass `None` to restore default locations.
+pub fn set_app_dir_root(root: impl Into<Option<PathBuf>>) {
+    APP_DIR_OVERRIDES.lock().unwrap().root = root.into();
+}
+
+/// Overrides location of given application directory.
+///
+/// This takes precedence over all other ways of locating application directories.
+/// Pass `None` to remove the override.
+pub fn set_app_dir(kind: AppDirKind, dir: impl Into<Option<PathBuf>>) {
+    let mut overrides = APP_DIR_OVERRIDES.lock().unwrap();
+    overrides.dirs.retain(|(k, _)| *k != kind);
+    if let Some(dir) = dir.into() {
+        overrides.dirs.push((kind, dir));
+    }
+}
+
+/// Enables portable mode where application directories are located next to the program executable (e.g. `<exe dir>/data`).
+///
+/// Portable mode can also be enabled by setting `<APP>_PORTABLE` environment variable (e.g. `MY_APP_PORTABLE=1`).
+pub fn set_app_portable(enabled: bool) {
+    APP_DIR_OVERRIDES.lock().unwrap().portable = enabled;
+}
+
+/// Sets file mode applied to application directories (and their missing parent directories) when they are created by `app_*` functions.
+///
+/// Directories are created with default mode (according to umask) if not set.
+#[cfg(all(target_family = "unix", feature = "file-mode"))]
+pub fn set_app_dir_mode(mode: impl Into<Option<Mode>>) {
+    *APP_DIR_MODE.lock().unwrap() = mode.into();
 }
 
 #[derive(Debug)]
@@ -41,6 +284,11 @@ pub enum AppDirError {
     NoProjectDir,
     NoBaseDir,
     NoUserDir,
+    NoRuntimeDir,
+    NoExecutableDir(io::Error),
+    CreateDirError(PathBuf, io::Error),
+    SetModeError(PathBuf, io::Error),
+    MigrationError(PathBuf, PathBuf, io::Error),
 }
 
 impl Display for AppDirError {
@@ -49,19 +297,29 @@ impl Display for AppDirError {
             AppDirError::NoProjectDir => write!(f, "getting project directories"),
             AppDirError::NoBaseDir  => write!(f, "getting base directories"),
             AppDirError::NoUserDir => write!(f, "getting user directories"),
+            AppDirError::NoRuntimeDir => write!(f, "getting runtime directory; not available on this platform or XDG_RUNTIME_DIR is not set"),
+            AppDirError::NoExecutableDir(_) => write!(f, "getting directory of program executable"),
+            AppDirError::CreateDirError(path, _) => write!(f, "creating application directory {:?}", path),
+            AppDirError::SetModeError(path, _) => write!(f, "setting file mode of application directory {:?}", path),
+            AppDirError::MigrationError(from, to, _) => write!(f, "migrating application directory {:?} to {:?}", from, to),
         }
     }
 }
 
 impl Error for AppDirError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AppDirError::NoExecutableDir(err) => Some(err),
+            AppDirError::CreateDirError(_, err) => Some(err),
+            AppDirError::SetModeError(_, err) => Some(err),
+            AppDirError::MigrationError(_, _, err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 pub fn project_dirs() -> Result<ProjectDirs, AppDirError> {
-    init_app_info_guess();
-    let app_info = APP_INFO.lock().unwrap();
-    let app_info = app_info.as_ref().unwrap();
-
-    ProjectDirs::from("", &app_info.author, &app_info.name).ok_or(AppDirError::NoProjectDir)
+    app_info().project_dirs()
 }
 
 pub fn base_dirs() -> Result<BaseDirs, AppDirError> {
@@ -72,28 +330,265 @@ pub fn user_dirs() -> Result<UserDirs, AppDirError> {
     UserDirs::new().ok_or(AppDirError::NoUserDir)
 }
 
+fn create_app_dir(path: &Path) -> Result<(), AppDirError> {
+    if path.is_dir() {
+        return Ok(())
+    }
+
+    #[cfg(all(target_family = "unix", feature = "file-mode"))]
+    if let Some(mode) = APP_DIR_MODE.lock().unwrap().as_ref() {
+        use std::os::unix::fs::DirBuilderExt;
+
+        // Apply the mode on creation (including parents) so the directory is never accessible with wider permissions
+        fs::DirBuilder::new().recursive(true).mode(mode.apply_to(0o040777) & 0o7777).create(path)
+            .map_err(|err| AppDirError::CreateDirError(path.to_owned(), err))?;
+        // Mode given on creation is limited by umask
+        mode.set_mode_path(path).map_err(|err| AppDirError::SetModeError(path.to_owned(), err))?;
+        return Ok(())
+    }
+
+    fs::create_dir_all(path).map_err(|err| AppDirError::CreateDirError(path.to_owned(), err))?;
+    Ok(())
+}
+
+fn app_dir(kind: AppDirKind, subdir: Option<&str>) -> Result<PathBuf, AppDirError> {
+    let dir = kind.resolve()?;
+    create_app_dir(&dir)?;
+    Ok(if let Some(subdir) = subdir {
+        let dir = dir.join(subdir);
+        create_app_dir(&dir)?;
+        dir
+    } else {
+        dir
+    })
+}
+
 /// Gets and creates if necessary application specific data directory.
 ///
-/// If subdir is given then additional sub directory is crated.
+/// If subdir is given then additional sub directory is created.
 pub fn app_data<'i>(subdir: impl Into<Option<&'i str>>) -> Result<PathBuf, AppDirError> {
-    let data_dir = project_dirs()?;
-    let data_dir = data_dir.data_dir();
-    Ok(if let Some(subdir) = subdir.into() {
-        data_dir.join(subdir)
-    } else {
-        data_dir.to_owned()
-    })
+    app_dir(AppDirKind::Data, subdir.into())
 }
 
 /// Gets and creates if necessary application specific cache directory.
 ///
-/// If subdir is given then additional sub directory is crated.
+/// If subdir is given then additional sub directory is created.
 pub fn app_cache<'i>(subdir: impl Into<Option<&'i str>>) -> Result<PathBuf, AppDirError> {
-    let cache_dir = project_dirs()?;
-    let cache_dir = cache_dir.cache_dir();
-    Ok(if let Some(subdir) = subdir.into() {
-        cache_dir.join(subdir)
-    } else {
-        cache_dir.to_owned()
-    })
+    app_dir(AppDirKind::Cache, subdir.into())
+}
+
+/// Gets and creates if necessary application specific configuration directory.
+///
+/// If subdir is given then additional sub directory is created.
+pub fn app_config<'i>(subdir: impl Into<Option<&'i str>>) -> Result<PathBuf, AppDirError> {
+    app_dir(AppDirKind::Config, subdir.into())
+}
+
+/// Gets and creates if necessary application specific state directory.
+///
+/// On platforms without dedicated state directory local data directory is used.
+/// If subdir is given then additional sub directory is created.
+pub fn app_state<'i>(subdir: impl Into<Option<&'i str>>) -> Result<PathBuf, AppDirError> {
+    app_dir(AppDirKind::State, subdir.into())
+}
+
+/// Gets and creates if necessary application specific runtime directory.
+///
+/// Unless overridden, runtime directory is only available on Linux when `XDG_RUNTIME_DIR` is set.
+/// If subdir is given then additional sub directory is created.
+pub fn app_runtime<'i>(subdir: impl Into<Option<&'i str>>) -> Result<PathBuf, AppDirError> {
+    app_dir(AppDirKind::Runtime, subdir.into())
+}
+
+/// Gets and creates if necessary application specific log directory.
+///
+/// Unless overridden, this is `log` sub directory of application state directory (see [app_state]).
+/// If subdir is given then additional sub directory is created.
+pub fn app_log<'i>(subdir: impl Into<Option<&'i str>>) -> Result<PathBuf, AppDirError> {
+    app_dir(AppDirKind::Log, subdir.into())
+}
+
+/// How application directories of previous application identity are migrated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirMigrationMethod {
+    /// Move (rename) the directory to the current location.
+    Move,
+    /// Create symbolic link at the current location pointing to the directory.
+    #[cfg(target_family = "unix")]
+    Symlink,
+}
+
+/// Application directory that was migrated from previous application identity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirMigration {
+    pub kind: AppDirKind,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Migrates data, configuration, cache and state directories of previous application identities (e.g. before the
+/// application was renamed or its author changed) to the current location.
+///
+/// Previous identities are checked in given order and the first existing directory of each kind is migrated.
+/// Directories that already exist at the current location or are overridden (see [set_app_dir]) are left untouched.
+/// Returns list of migrated directories.
+pub fn migrate_app_dirs(previous: impl IntoIterator<Item = impl Into<AppInfo>>, method: DirMigrationMethod) -> Result<Vec<DirMigration>, AppDirError> {
+    let current = project_dirs()?;
+    let previous = previous.into_iter().map(|info| info.into().project_dirs()).collect::<Result<Vec<_>, _>>()?;
+    let mut migrations = Vec::new();
+
+    for kind in [AppDirKind::Data, AppDirKind::Config, AppDirKind::Cache, AppDirKind::State] {
+        if kind.override_dir()?.is_some() {
+            continue
+        }
+
+        let to = kind.project_dir(&current)?;
+        if to.symlink_metadata().is_ok() {
+            continue
+        }
+
+        let from = match previous.iter().map(|dirs| kind.project_dir(dirs)).filter_map(Result::ok).find(|from| from != &to && from.is_dir()) {
+            Some(from) => from,
+            None => continue,
+        };
+
+        let migration_error = |err| AppDirError::MigrationError(from.clone(), to.clone(), err);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(migration_error)?;
+        }
+
+        match method {
+            DirMigrationMethod::Move => fs::rename(&from, &to).map_err(migration_error)?,
+            #[cfg(target_family = "unix")]
+            DirMigrationMethod::Symlink => std::os::unix::fs::symlink(&from, &to).map_err(migration_error)?,
+        }
+
+        #[cfg(feature = "log")]
+        log::info!("Migrated application {} directory {:?} to {:?} ({:?})", kind.dir_name(), from, to, method);
+
+        migrations.push(DirMigration { kind, from, to });
+    }
+
+    Ok(migrations)
+}
+
+/// Command line arguments overriding location of application directories.
+#[cfg(feature = "clap")]
+#[derive(Debug, clap::Args)]
+pub struct ArgsAppDirs {
+    /// Keep application data, cache, configuration and state next to the program executable
+    #[arg(long)]
+    pub portable: bool,
+
+    /// Location of application data directory
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Location of application cache directory
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Location of application configuration directory
+    #[arg(long, value_name = "DIR")]
+    pub config_dir: Option<PathBuf>,
+
+    /// Location of application state directory
+    #[arg(long, value_name = "DIR")]
+    pub state_dir: Option<PathBuf>,
+}
+
+#[cfg(feature = "clap")]
+impl ArgsAppDirs {
+    /// Applies overrides so that they are honored by `app_*` functions.
+    pub fn apply(&self) {
+        if self.portable {
+            set_app_portable(true);
+        }
+        for (kind, dir) in [
+            (AppDirKind::Data, &self.data_dir),
+            (AppDirKind::Cache, &self.cache_dir),
+            (AppDirKind::Config, &self.config_dir),
+            (AppDirKind::State, &self.state_dir),
+        ] {
+            if let Some(dir) = dir {
+                set_app_dir(kind, dir.clone());
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux", feature = "tempfile"))]
+mod tests {
+    use super::*;
+
+    // All steps share global application info and overrides so they need to run in sequence
+    #[test]
+    fn app_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        for (var, dir) in [("XDG_DATA_HOME", "data"), ("XDG_CACHE_HOME", "cache"), ("XDG_CONFIG_HOME", "config"), ("XDG_STATE_HOME", "state"), ("XDG_RUNTIME_DIR", "run")] {
+            env::set_var(var, root.path().join("xdg").join(dir));
+        }
+        init_app_info_from(AppInfo::new("cotton-test", "Cotton").with_version("1.2.3"));
+        assert_eq!(app_info().user_agent(), "cotton-test/1.2.3");
+        #[cfg(feature = "file-mode")]
+        set_app_dir_mode(Mode::from(0o700));
+
+        let data = app_data("foo").unwrap();
+        assert_eq!(data, root.path().join("xdg/data/cotton-test/foo"));
+        assert!(data.is_dir());
+        assert!(app_cache(None).unwrap().starts_with(root.path().join("xdg/cache")));
+        assert!(app_config(None).unwrap().starts_with(root.path().join("xdg/config")));
+        assert!(app_state(None).unwrap().starts_with(root.path().join("xdg/state")));
+        assert!(app_runtime(None).unwrap().starts_with(root.path().join("xdg/run")));
+        assert_eq!(app_log("bar").unwrap(), root.path().join("xdg/state/cotton-test/log/bar"));
+
+        #[cfg(feature = "file-mode")]
+        assert_eq!(Mode::from_path(&data).unwrap().mode() & 0o777, 0o700);
+        #[cfg(feature = "file-mode")]
+        assert_eq!(Mode::from_path(root.path().join("xdg/data")).unwrap().mode() & 0o777, 0o700);
+
+        assert_eq!(AppDirKind::Data.env_var(), "COTTON_TEST_DATA_DIR");
+        env::set_var("COTTON_TEST_DATA_DIR", root.path().join("env"));
+        assert_eq!(app_data(None).unwrap(), root.path().join("env"));
+
+        set_app_dir_root(root.path().join("root"));
+        assert_eq!(app_data(None).unwrap(), root.path().join("root/data"));
+        assert_eq!(app_log(None).unwrap(), root.path().join("root/log"));
+
+        set_app_dir(AppDirKind::Data, root.path().join("explicit"));
+        assert_eq!(app_data(None).unwrap(), root.path().join("explicit"));
+        set_app_dir(AppDirKind::Data, None);
+        set_app_dir_root(None);
+        env::remove_var("COTTON_TEST_DATA_DIR");
+
+        set_app_portable(true);
+        assert_eq!(AppDirKind::Cache.resolve().unwrap(), env::current_exe().unwrap().parent().unwrap().join("cache"));
+        set_app_portable(false);
+        assert_eq!(app_data(None).unwrap(), root.path().join("xdg/data/cotton-test"));
+
+        fs::remove_dir_all(root.path().join("xdg/data/cotton-test")).unwrap();
+        fs::create_dir_all(root.path().join("xdg/data/cotton-old")).unwrap();
+        fs::write(root.path().join("xdg/data/cotton-old/foo"), "bar").unwrap();
+        fs::create_dir_all(root.path().join("xdg/config/cotton-old")).unwrap();
+
+        let migrations = migrate_app_dirs([("cotton-older", "Cotton"), ("cotton-old", "Cotton")], DirMigrationMethod::Move).unwrap();
+        assert_eq!(migrations, [DirMigration {
+            kind: AppDirKind::Data,
+            from: root.path().join("xdg/data/cotton-old"),
+            to: root.path().join("xdg/data/cotton-test"),
+        }]);
+        assert_eq!(fs::read_to_string(app_data(None).unwrap().join("foo")).unwrap(), "bar");
+    }
+
+    #[cfg(feature = "clap")]
+    #[test]
+    fn app_info_from_command() {
+        let command = clap::Command::new("foo").author("Bar").version("0.1.0");
+        let info = AppInfo::from_command(&command).with_qualifier("com").with_organization("Baz");
+
+        assert_eq!(info.name, "foo");
+        assert_eq!(info.author, "Bar");
+        assert_eq!(info.organization(), "Baz");
+        assert_eq!(info.to_string(), "foo 0.1.0");
+    }
 }
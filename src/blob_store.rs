@@ -0,0 +1,392 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::fmt::{self, Display};
+use std::error::Error;
+use std::time::{Duration, SystemTime};
+
+use tempfile::Builder as TempFileBuilder;
+use filetime::{set_file_mtime, FileTime};
+
+use crate::hashing::{Digest, DigestError, HashingReader, HashingWriter};
+use crate::app_dir::{app_cache, AppDirError};
+
+const TEMP_FILE_PREFIX: &str = ".tmp";
+// Temporary files not written to for this long are left by interrupted writes
+const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug)]
+pub enum BlobStoreError {
+    AppDirError(AppDirError),
+    IoError(PathBuf, io::Error),
+    IntegrityError(PathBuf, DigestError),
+}
+
+impl From<AppDirError> for BlobStoreError {
+    fn from(err: AppDirError) -> BlobStoreError {
+        BlobStoreError::AppDirError(err)
+    }
+}
+
+impl Display for BlobStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlobStoreError::AppDirError(_) => write!(f, "failed to locate blob store directory"),
+            BlobStoreError::IoError(path, _) => write!(f, "I/O error while accessing blob store path {:?}", path),
+            BlobStoreError::IntegrityError(path, _) => write!(f, "blob {:?} failed integrity check", path),
+        }
+    }
+}
+
+impl Error for BlobStoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BlobStoreError::AppDirError(err) => Some(err),
+            BlobStoreError::IoError(_, err) => Some(err),
+            BlobStoreError::IntegrityError(_, err) => Some(err),
+        }
+    }
+}
+
+/// Information about blob stored in [BlobStore].
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub digest: Digest,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Content-addressed store of blobs keyed by their SHA2-256 digest.
+///
+/// Blobs are stored in directories sharded by first two hex digits of the digest and are written
+/// atomically by persisting a temporary file within the store.
+/// Reading a blob updates its modification time so that garbage collection removes least recently used blobs first.
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    root: PathBuf,
+}
+
+impl BlobStore {
+    /// Opens blob store in given directory, creating it if necessary.
+    pub fn open(root: impl Into<PathBuf>) -> Result<BlobStore, BlobStoreError> {
+        let root = root.into();
+        fs::create_dir_all(&root).map_err(|err| BlobStoreError::IoError(root.clone(), err))?;
+        Ok(BlobStore { root })
+    }
+
+    /// Opens blob store in application specific cache sub directory with given name.
+    pub fn open_app_cache(name: &str) -> Result<BlobStore, BlobStoreError> {
+        BlobStore::open(app_cache(name)?)
+    }
+
+    /// Returns root directory of the store.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns path under which blob with given digest is stored.
+    pub fn path(&self, digest: &Digest) -> PathBuf {
+        let hex = digest.to_hex();
+        self.root.join(&hex[..2]).join(&hex[2..])
+    }
+
+    /// Returns true if blob with given digest is in the store.
+    pub fn contains(&self, digest: &Digest) -> bool {
+        self.path(digest).is_file()
+    }
+
+    /// Stores content read from reader and returns its digest.
+    pub fn put_reader<R: Read>(&self, reader: &mut R) -> Result<Digest, BlobStoreError> {
+        let io_error = |err| BlobStoreError::IoError(self.root.clone(), err);
+
+        let temp = TempFileBuilder::new().prefix(TEMP_FILE_PREFIX).tempfile_in(&self.root).map_err(io_error)?;
+        let mut writer = HashingWriter::new(temp);
+        io::copy(reader, &mut writer).map_err(io_error)?;
+        let (temp, digest) = writer.finish().map_err(io_error)?;
+
+        let path = self.path(&digest);
+        if path.is_file() {
+            set_file_mtime(&path, FileTime::now()).map_err(|err| BlobStoreError::IoError(path.clone(), err))?;
+            return Ok(digest)
+        }
+
+        let shard = path.parent().expect("blob path has shard directory");
+        fs::create_dir_all(shard).map_err(|err| BlobStoreError::IoError(shard.to_owned(), err))?;
+        temp.persist(&path).map_err(|err| BlobStoreError::IoError(path.clone(), err.error))?;
+
+        Ok(digest)
+    }
+
+    /// Stores given bytes and returns their digest.
+    pub fn put_bytes(&self, bytes: impl AsRef<[u8]>) -> Result<Digest, BlobStoreError> {
+        self.put_reader(&mut bytes.as_ref())
+    }
+
+    /// Stores content of a file and returns its digest.
+    pub fn put_file(&self, path: impl AsRef<Path>) -> Result<Digest, BlobStoreError> {
+        let path = path.as_ref();
+        let mut file = BufReader::new(File::open(path).map_err(|err| BlobStoreError::IoError(path.to_owned(), err))?);
+        self.put_reader(&mut file)
+    }
+
+    /// Opens blob with given digest for reading.
+    ///
+    /// Returned reader verifies integrity of the blob and fails with [io::ErrorKind::InvalidData] error at the end
+    /// of the stream if content does not match the digest.
+    pub fn get(&self, digest: &Digest) -> Result<Option<HashingReader<BufReader<File>>>, BlobStoreError> {
+        let path = self.path(digest);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(BlobStoreError::IoError(path, err)),
+        };
+        set_file_mtime(&path, FileTime::now()).map_err(|err| BlobStoreError::IoError(path.clone(), err))?;
+
+        Ok(Some(HashingReader::with_expected(BufReader::new(file), digest.clone())))
+    }
+
+    /// Reads and verifies content of blob with given digest.
+    pub fn get_bytes(&self, digest: &Digest) -> Result<Option<Vec<u8>>, BlobStoreError> {
+        let mut reader = match self.get(digest)? {
+            Some(reader) => reader,
+            None => return Ok(None),
+        };
+
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(|err| self.read_error(digest, err))?;
+        Ok(Some(bytes))
+    }
+
+    /// Copies verified content of blob with given digest to a file at given path.
+    ///
+    /// Content is written to a temporary file next to the destination that is persisted only if it matches the digest;
+    /// the temporary file may be left behind if the process is killed.
+    /// Returns false if blob is not in the store.
+    pub fn get_file(&self, digest: &Digest, path: impl AsRef<Path>) -> Result<bool, BlobStoreError> {
+        let path = path.as_ref();
+        let mut reader = match self.get(digest)? {
+            Some(reader) => reader,
+            None => return Ok(false),
+        };
+
+        let io_error = |err| BlobStoreError::IoError(path.to_owned(), err);
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut temp = TempFileBuilder::new().prefix(TEMP_FILE_PREFIX).tempfile_in(dir).map_err(io_error)?;
+        io::copy(&mut reader, &mut temp).map_err(|err| self.read_error(digest, err))?;
+        temp.flush().map_err(io_error)?;
+        temp.persist(path).map_err(|err| io_error(err.error))?;
+        Ok(true)
+    }
+
+    /// Verifies integrity of blob with given digest.
+    ///
+    /// Returns false if content of the blob does not match the digest or `None` if blob is not in the store.
+    pub fn verify(&self, digest: &Digest) -> Result<Option<bool>, BlobStoreError> {
+        let path = self.path(digest);
+        let mut file = match File::open(&path) {
+            Ok(file) => BufReader::new(file),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(BlobStoreError::IoError(path, err)),
+        };
+        Ok(Some(&Digest::from_reader(&mut file).map_err(|err| BlobStoreError::IoError(path, err))? == digest))
+    }
+
+    /// Removes blob with given digest from the store.
+    ///
+    /// Returns false if blob was not in the store.
+    pub fn remove(&self, digest: &Digest) -> Result<bool, BlobStoreError> {
+        let path = self.path(digest);
+        match fs::remove_file(&path) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(BlobStoreError::IoError(path, err)),
+        }
+
+        // Shard directory is removed only if empty
+        path.parent().map(fs::remove_dir);
+        Ok(true)
+    }
+
+    /// Lists all blobs in the store.
+    pub fn blobs(&self) -> Result<Vec<Blob>, BlobStoreError> {
+        let io_error = |path: &Path| { let path = path.to_owned(); move |err| BlobStoreError::IoError(path, err) };
+        let mut blobs = Vec::new();
+
+        for shard in fs::read_dir(&self.root).map_err(io_error(&self.root))? {
+            let shard = shard.map_err(io_error(&self.root))?;
+            let shard_name = shard.file_name();
+            let shard_name = match shard_name.to_str() {
+                Some(name) if name.len() == 2 && shard.path().is_dir() => name.to_owned(),
+                _ => continue,
+            };
+
+            for entry in fs::read_dir(shard.path()).map_err(io_error(&shard.path()))? {
+                let entry = entry.map_err(io_error(&shard.path()))?;
+                let digest = match entry.file_name().to_str().and_then(|name| Digest::from_hex(&format!("{}{}", shard_name, name)).ok()) {
+                    Some(digest) => digest,
+                    None => continue,
+                };
+
+                let path = entry.path();
+                let metadata = entry.metadata().map_err(io_error(&path))?;
+                let modified = metadata.modified().map_err(io_error(&path))?;
+
+                blobs.push(Blob {
+                    digest,
+                    path,
+                    size: metadata.len(),
+                    modified,
+                });
+            }
+        }
+
+        Ok(blobs)
+    }
+
+    /// Returns total size in bytes of all blobs in the store.
+    pub fn size(&self) -> Result<u64, BlobStoreError> {
+        Ok(self.blobs()?.iter().map(|blob| blob.size).sum())
+    }
+
+    /// Removes blobs that were not stored or read for longer than given age.
+    ///
+    /// Temporary files left by interrupted writes are removed as well.
+    /// Returns removed blobs.
+    pub fn remove_older_than(&self, age: Duration) -> Result<Vec<Blob>, BlobStoreError> {
+        self.remove_stale_temp_files()?;
+        let now = SystemTime::now();
+        let mut removed = Vec::new();
+
+        for blob in self.blobs()? {
+            if now.duration_since(blob.modified).map(|blob_age| blob_age > age).unwrap_or(false) {
+                self.remove(&blob.digest)?;
+                removed.push(blob);
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Removes least recently stored or read blobs until total size of the store is not greater than given size in bytes.
+    ///
+    /// Temporary files left by interrupted writes are removed as well.
+    /// Returns removed blobs.
+    pub fn shrink_to(&self, max_size: u64) -> Result<Vec<Blob>, BlobStoreError> {
+        self.remove_stale_temp_files()?;
+        let mut blobs = self.blobs()?;
+        let mut size: u64 = blobs.iter().map(|blob| blob.size).sum();
+        let mut removed = Vec::new();
+
+        blobs.sort_by_key(|blob| blob.modified);
+
+        for blob in blobs {
+            if size <= max_size {
+                break
+            }
+            self.remove(&blob.digest)?;
+            size -= blob.size;
+            removed.push(blob);
+        }
+
+        Ok(removed)
+    }
+
+    fn remove_stale_temp_files(&self) -> Result<(), BlobStoreError> {
+        let io_error = |path: &Path| { let path = path.to_owned(); move |err| BlobStoreError::IoError(path, err) };
+        let now = SystemTime::now();
+
+        for entry in fs::read_dir(&self.root).map_err(io_error(&self.root))? {
+            let entry = entry.map_err(io_error(&self.root))?;
+            if !entry.file_name().to_str().map(|name| name.starts_with(TEMP_FILE_PREFIX)).unwrap_or(false) {
+                continue
+            }
+
+            let path = entry.path();
+            let modified = entry.metadata().and_then(|metadata| metadata.modified()).map_err(io_error(&path))?;
+            if now.duration_since(modified).map(|age| age > STALE_TEMP_FILE_AGE).unwrap_or(false) {
+                match fs::remove_file(&path) {
+                    Ok(()) => (),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                    Err(err) => return Err(BlobStoreError::IoError(path, err)),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_error(&self, digest: &Digest, err: io::Error) -> BlobStoreError {
+        let path = self.path(digest);
+        if let Some(DigestError::DigestMissmatch { got, expected }) = err.get_ref().and_then(|err| err.downcast_ref::<DigestError>()) {
+            return BlobStoreError::IntegrityError(path, DigestError::DigestMissmatch { got: got.clone(), expected: expected.clone() })
+        }
+        BlobStoreError::IoError(path, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn put_and_get() {
+        let dir = tempdir().unwrap();
+        let store = BlobStore::open(dir.path()).unwrap();
+
+        let digest = store.put_bytes("foobar").unwrap();
+        assert_eq!(digest, Digest::from_bytes("foobar"));
+        assert_eq!(store.path(&digest), dir.path().join("c3").join("ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2"));
+        assert!(store.contains(&digest));
+        assert_eq!(store.put_bytes("foobar").unwrap(), digest);
+        assert_eq!(store.get_bytes(&digest).unwrap().unwrap(), b"foobar");
+        assert!(store.get_bytes(&Digest::from_bytes("baz")).unwrap().is_none());
+
+        let copy = dir.path().join("copy");
+        assert!(store.get_file(&digest, &copy).unwrap());
+        assert_eq!(fs::read(&copy).unwrap(), b"foobar");
+        assert_eq!(store.verify(&digest).unwrap(), Some(true));
+        assert_eq!(store.verify(&Digest::from_bytes("baz")).unwrap(), None);
+
+        fs::write(store.path(&digest), "foobaz").unwrap();
+        assert_eq!(store.verify(&digest).unwrap(), Some(false));
+        assert_matches::assert_matches!(store.get_bytes(&digest), Err(BlobStoreError::IntegrityError(..)));
+
+        // Corrupted blob does not replace existing destination file
+        assert_matches::assert_matches!(store.get_file(&digest, &copy), Err(BlobStoreError::IntegrityError(..)));
+        assert_eq!(fs::read(&copy).unwrap(), b"foobar");
+    }
+
+    #[test]
+    fn gc() {
+        let dir = tempdir().unwrap();
+        let store = BlobStore::open(dir.path()).unwrap();
+
+        let old = store.put_bytes("old").unwrap();
+        set_file_mtime(store.path(&old), FileTime::from_unix_time(0, 0)).unwrap();
+        let new = store.put_bytes("new!").unwrap();
+
+        assert_eq!(store.blobs().unwrap().len(), 2);
+        assert_eq!(store.size().unwrap(), 7);
+
+        let removed = store.shrink_to(5).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].digest, old);
+        assert!(!dir.path().join(&old.to_hex()[..2]).exists());
+
+        let stale_temp = dir.path().join(".tmpstale");
+        let fresh_temp = dir.path().join(".tmpfresh");
+        fs::write(&stale_temp, "partial").unwrap();
+        set_file_mtime(&stale_temp, FileTime::from_unix_time(0, 0)).unwrap();
+        fs::write(&fresh_temp, "partial").unwrap();
+
+        assert!(store.remove_older_than(Duration::from_secs(3600)).unwrap().is_empty());
+        assert!(store.contains(&new));
+        assert!(!stale_temp.exists());
+        assert!(fresh_temp.exists());
+    }
+}
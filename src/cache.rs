@@ -0,0 +1,371 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::fmt::{self, Display};
+use std::error::Error;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use tempfile::NamedTempFile;
+
+use crate::hashing::Digest;
+use crate::app_dir::{app_cache, AppDirError};
+
+#[derive(Debug)]
+pub enum DiskCacheError {
+    AppDirError(AppDirError),
+    IoError(PathBuf, io::Error),
+}
+
+impl From<AppDirError> for DiskCacheError {
+    fn from(err: AppDirError) -> DiskCacheError {
+        DiskCacheError::AppDirError(err)
+    }
+}
+
+impl Display for DiskCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskCacheError::AppDirError(_) => write!(f, "failed to locate cache directory"),
+            DiskCacheError::IoError(path, _) => write!(f, "I/O error while accessing cache path {:?}", path),
+        }
+    }
+}
+
+impl Error for DiskCacheError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DiskCacheError::AppDirError(err) => Some(err),
+            DiskCacheError::IoError(_, err) => Some(err),
+        }
+    }
+}
+
+/// How [DiskCache] uses cached values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Use cached values and cache computed ones.
+    Use,
+    /// Ignore cached values but cache computed ones.
+    Refresh,
+    /// Do not use the cache at all.
+    Disabled,
+}
+
+/// Disk cache of results of expensive computations.
+///
+/// Entries are keyed by SHA2-256 digest of the length prefixed computation inputs and stored in directories
+/// sharded by first two hex digits of the key.
+/// Entries older than TTL are not used and oldest entries are evicted when cache grows above maximum size.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    root: PathBuf,
+    ttl: Option<Duration>,
+    max_size: Option<u64>,
+    mode: CacheMode,
+}
+
+impl DiskCache {
+    /// Opens cache in given directory, creating it if necessary.
+    pub fn open(root: impl Into<PathBuf>) -> Result<DiskCache, DiskCacheError> {
+        let root = root.into();
+        fs::create_dir_all(&root).map_err(|err| DiskCacheError::IoError(root.clone(), err))?;
+        Ok(DiskCache {
+            root,
+            ttl: None,
+            max_size: None,
+            mode: CacheMode::Use,
+        })
+    }
+
+    /// Opens cache in application specific cache sub directory with given name.
+    pub fn open_app(name: &str) -> Result<DiskCache, DiskCacheError> {
+        DiskCache::open(app_cache(name)?)
+    }
+
+    /// Sets how long cached values are valid for.
+    pub fn with_ttl(mut self, ttl: Duration) -> DiskCache {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets maximum total size in bytes of cached values.
+    pub fn with_max_size(mut self, max_size: u64) -> DiskCache {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets cache mode (e.g. from [ArgsCache]).
+    pub fn with_mode(mut self, mode: CacheMode) -> DiskCache {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns root directory of the cache.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(&key[..2]).join(&key[2..])
+    }
+
+    /// Gets cached value for given key parts if available and not expired.
+    pub fn get_bytes<S: AsRef<[u8]>>(&self, key: impl IntoIterator<Item = S, IntoIter = impl Iterator<Item = S>>) -> Result<Option<Vec<u8>>, DiskCacheError> {
+        self.get_entry(&cache_key(key))
+    }
+
+    /// Stores value under given key parts and evicts oldest entries if cache exceeds maximum size.
+    pub fn put_bytes<S: AsRef<[u8]>>(&self, key: impl IntoIterator<Item = S, IntoIter = impl Iterator<Item = S>>, value: &[u8]) -> Result<(), DiskCacheError> {
+        self.put_entry(&cache_key(key), value)
+    }
+
+    fn get_entry(&self, key: &str) -> Result<Option<Vec<u8>>, DiskCacheError> {
+        if self.mode != CacheMode::Use {
+            return Ok(None)
+        }
+
+        let path = self.path(key);
+        let io_error = |err| DiskCacheError::IoError(path.clone(), err);
+
+        let modified = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(io_error(err)),
+        };
+
+        if self.is_expired(modified) {
+            remove_entry(&path).map_err(io_error)?;
+            return Ok(None)
+        }
+
+        match fs::read(&path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(io_error(err)),
+        }
+    }
+
+    fn put_entry(&self, key: &str, value: &[u8]) -> Result<(), DiskCacheError> {
+        if self.mode == CacheMode::Disabled {
+            return Ok(())
+        }
+
+        let path = self.path(key);
+        let io_error = |err| DiskCacheError::IoError(path.clone(), err);
+        let shard = path.parent().expect("cache entry path has shard directory");
+
+        fs::create_dir_all(shard).map_err(io_error)?;
+        let mut temp = NamedTempFile::new_in(&self.root).map_err(io_error)?;
+        temp.write_all(value).map_err(io_error)?;
+        temp.persist(&path).map_err(|err| io_error(err.error))?;
+
+        if let Some(max_size) = self.max_size {
+            self.shrink_to(max_size)?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets cached value for given key parts or computes it with given function and caches it.
+    ///
+    /// Values are converted to and from string with [Display] and [FromStr]; cached values that fail to parse are
+    /// computed again.
+    pub fn cached<S, T, E>(&self, key: impl IntoIterator<Item = S, IntoIter = impl Iterator<Item = S>>, compute: impl FnOnce() -> Result<T, E>) -> Result<T, E>
+    where S: AsRef<[u8]>, T: Display + FromStr, E: From<DiskCacheError> {
+        let key = cache_key(key);
+
+        if let Some(value) = self.get_entry(&key)? {
+            if let Some(value) = String::from_utf8(value).ok().and_then(|value| value.parse().ok()) {
+                return Ok(value)
+            }
+        }
+
+        let value = compute()?;
+        self.put_entry(&key, value.to_string().as_bytes())?;
+        Ok(value)
+    }
+
+    /// Like [DiskCache::cached] but for byte values.
+    pub fn cached_bytes<S, E>(&self, key: impl IntoIterator<Item = S, IntoIter = impl Iterator<Item = S>>, compute: impl FnOnce() -> Result<Vec<u8>, E>) -> Result<Vec<u8>, E>
+    where S: AsRef<[u8]>, E: From<DiskCacheError> {
+        let key = cache_key(key);
+
+        if let Some(value) = self.get_entry(&key)? {
+            return Ok(value)
+        }
+
+        let value = compute()?;
+        self.put_entry(&key, &value)?;
+        Ok(value)
+    }
+
+    /// Removes expired entries.
+    pub fn purge_expired(&self) -> Result<(), DiskCacheError> {
+        for (path, _, modified) in self.entries()? {
+            if self.is_expired(modified) {
+                remove_entry(&path).map_err(|err| DiskCacheError::IoError(path.clone(), err))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) -> Result<(), DiskCacheError> {
+        for (path, _, _) in self.entries()? {
+            remove_entry(&path).map_err(|err| DiskCacheError::IoError(path.clone(), err))?;
+        }
+        Ok(())
+    }
+
+    /// Returns total size in bytes of cached values.
+    pub fn size(&self) -> Result<u64, DiskCacheError> {
+        Ok(self.entries()?.iter().map(|(_, size, _)| size).sum())
+    }
+
+    /// Removes oldest entries until total size of cached values is not greater than given size in bytes.
+    pub fn shrink_to(&self, max_size: u64) -> Result<(), DiskCacheError> {
+        let mut entries = self.entries()?;
+        let mut size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+
+        entries.sort_by_key(|(_, _, modified)| *modified);
+
+        for (path, entry_size, _) in entries {
+            if size <= max_size {
+                break
+            }
+            remove_entry(&path).map_err(|err| DiskCacheError::IoError(path.clone(), err))?;
+            size -= entry_size;
+        }
+
+        Ok(())
+    }
+
+    fn is_expired(&self, modified: SystemTime) -> bool {
+        self.ttl.map(|ttl| SystemTime::now().duration_since(modified).map(|age| age > ttl).unwrap_or(false)).unwrap_or(false)
+    }
+
+    fn entries(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>, DiskCacheError> {
+        let io_error = |path: &Path| { let path = path.to_owned(); move |err| DiskCacheError::IoError(path, err) };
+        let mut entries = Vec::new();
+
+        for shard in fs::read_dir(&self.root).map_err(io_error(&self.root))? {
+            let shard = shard.map_err(io_error(&self.root))?.path();
+            if !shard.is_dir() {
+                continue
+            }
+
+            for entry in fs::read_dir(&shard).map_err(io_error(&shard))? {
+                let entry = entry.map_err(io_error(&shard))?;
+                let path = entry.path();
+                let metadata = entry.metadata().map_err(io_error(&path))?;
+                let modified = metadata.modified().map_err(io_error(&path))?;
+                entries.push((path, metadata.len(), modified));
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+// Each part is prefixed with its length so that e.g. `["ab", "c"]` and `["a", "bc"]` give different keys
+fn cache_key<S: AsRef<[u8]>>(parts: impl IntoIterator<Item = S, IntoIter = impl Iterator<Item = S>>) -> String {
+    Digest::from_buffers(parts.into_iter().flat_map(|part| {
+        let part = part.as_ref();
+        [(part.len() as u64).to_le_bytes().to_vec(), part.to_vec()]
+    })).to_hex()
+}
+
+fn remove_entry(path: &Path) -> Result<(), io::Error> {
+    match fs::remove_file(path) {
+        Ok(()) => (),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+        Err(err) => return Err(err),
+    }
+    // Shard directory is removed only if empty
+    path.parent().map(fs::remove_dir);
+    Ok(())
+}
+
+/// Command line arguments controlling use of [DiskCache].
+#[cfg(feature = "clap")]
+#[derive(Debug, clap::Args)]
+pub struct ArgsCache {
+    /// Do not use or store cached results
+    #[arg(long = "no-cache", conflicts_with = "refresh")]
+    pub no_cache: bool,
+
+    /// Ignore cached results and store fresh ones
+    #[arg(long = "refresh-cache")]
+    pub refresh: bool,
+}
+
+#[cfg(feature = "clap")]
+impl ArgsCache {
+    /// Returns cache mode selected by the arguments.
+    pub fn mode(&self) -> CacheMode {
+        if self.no_cache {
+            CacheMode::Disabled
+        } else if self.refresh {
+            CacheMode::Refresh
+        } else {
+            CacheMode::Use
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use filetime::{set_file_mtime, FileTime};
+
+    #[test]
+    fn cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::open(dir.path()).unwrap().with_ttl(Duration::from_secs(3600));
+        let calls = Cell::new(0);
+        let compute = || -> Result<u64, DiskCacheError> { calls.set(calls.get() + 1); Ok(42) };
+
+        assert_eq!(cache.cached(["answer", "v1"], compute).unwrap(), 42);
+        assert_eq!(cache.cached(["answer", "v1"], compute).unwrap(), 42);
+        assert_eq!(calls.get(), 1);
+
+        cache.clone().with_mode(CacheMode::Refresh).cached(["answer", "v1"], compute).unwrap();
+        assert_eq!(calls.get(), 2);
+        cache.clone().with_mode(CacheMode::Disabled).cached(["answer", "v2"], compute).unwrap();
+        assert_eq!(calls.get(), 3);
+        assert!(cache.get_bytes(["answer", "v2"]).unwrap().is_none());
+
+        set_file_mtime(cache.path(&cache_key(["answer", "v1"])), FileTime::from_unix_time(0, 0)).unwrap();
+        assert!(cache.get_bytes(["answer", "v1"]).unwrap().is_none());
+        cache.cached(["answer", "v1"], compute).unwrap();
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[test]
+    fn max_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::open(dir.path()).unwrap();
+
+        cache.put_bytes(["old"], b"12345").unwrap();
+        set_file_mtime(cache.path(&cache_key(["old"])), FileTime::from_unix_time(0, 0)).unwrap();
+        let cache = cache.with_max_size(8);
+        cache.put_bytes(["new"], b"12345").unwrap();
+
+        assert_eq!(cache.size().unwrap(), 5);
+        assert!(cache.get_bytes(["old"]).unwrap().is_none());
+        assert_eq!(cache.get_bytes(["new"]).unwrap().unwrap(), b"12345");
+    }
+
+    #[test]
+    fn key_parts() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::open(dir.path()).unwrap();
+
+        cache.put_bytes(["ab", "c"], b"one").unwrap();
+        assert!(cache.get_bytes(["a", "bc"]).unwrap().is_none());
+        assert!(cache.get_bytes(["abc"]).unwrap().is_none());
+        assert_eq!(cache.get_bytes(["ab", "c"]).unwrap().unwrap(), b"one");
+    }
+}
@@ -0,0 +1,360 @@
+use std::fmt::{self, Display};
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+
+use crate::time::{sleep_until_interruptible, Interrupted};
+
+const MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAY_NAMES: [&str; 8] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+// Schedules for February 29th may not fire for up to 8 years (e.g. 2096 to 2104)
+const SEARCH_YEARS: i32 = 9;
+// Re-check wall clock periodically while waiting in case it was adjusted or the system was suspended
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CronError {
+    FieldCount(usize),
+    InvalidField { field: &'static str, value: String },
+    UnknownMacro(String),
+    NeverFires,
+}
+
+impl Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CronError::FieldCount(count) => write!(f, "cron expression has {} fields; expected 5 or 6", count),
+            CronError::InvalidField { field, value } => write!(f, "invalid {} field {:?} in cron expression", field, value),
+            CronError::UnknownMacro(name) => write!(f, "unknown cron macro {:?}", name),
+            CronError::NeverFires => write!(f, "cron expression never fires"),
+        }
+    }
+}
+
+impl Error for CronError {}
+
+/// Parsed cron expression.
+///
+/// Supports 5 field (`minute hour day-of-month month day-of-week`) and 6 field (with leading `second`) expressions.
+/// Fields can be `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps (`*/15`, `0-30/10`, `5/10`);
+/// months and days of week can be given by their English three letter names (`JAN`, `MON-FRI`), Sunday is `0` or `7`.
+/// Macros `@yearly`, `@annually`, `@monthly`, `@weekly`, `@daily`, `@midnight` and `@hourly` are supported as well.
+///
+/// As in Vixie cron, when both day-of-month and day-of-week fields are restricted (do not start with `*`) the schedule fires
+/// when either matches; otherwise both have to match (e.g. `*/2` in day-of-month field fires every other day).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+fn parse_value(value: &str, names: &[&str], name_offset: u32) -> Option<u32> {
+    value.parse().ok().or_else(|| {
+        let value = value.to_lowercase();
+        names.iter().position(|name| *name == value).map(|index| index as u32 + name_offset)
+    })
+}
+
+fn parse_field(field: &'static str, value: &str, min: u32, max: u32, names: &[&str], name_offset: u32) -> Result<u64, CronError> {
+    let invalid = || CronError::InvalidField { field, value: value.to_owned() };
+    let mut bits = 0;
+
+    for part in value.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(invalid)?)),
+            None => (part, None),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" || range == "?" => (min, max),
+            Some((start, end)) => (parse_value(start, names, name_offset).ok_or_else(invalid)?, parse_value(end, names, name_offset).ok_or_else(invalid)?),
+            None => {
+                let start = parse_value(range, names, name_offset).ok_or_else(invalid)?;
+                (start, if step.is_some() { max } else { start })
+            }
+        };
+
+        if start < min || end > max || start > end {
+            return Err(invalid())
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1).and_then(|date| date.pred_opt()).map(|date| date.day()).unwrap_or(31)
+}
+
+impl CronSchedule {
+    /// Parses cron expression.
+    pub fn parse(expression: &str) -> Result<CronSchedule, CronError> {
+        let expression = expression.trim();
+        let expanded = match expression {
+            "@yearly" | "@annually" => "0 0 0 1 1 *",
+            "@monthly" => "0 0 0 1 * *",
+            "@weekly" => "0 0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 0 * * *",
+            "@hourly" => "0 0 * * * *",
+            expr if expr.starts_with('@') => return Err(CronError::UnknownMacro(expr.to_owned())),
+            expr => expr,
+        };
+
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        let (seconds, fields) = match fields.len() {
+            5 => (1, &fields[..]),
+            6 => (parse_field("second", fields[0], 0, 59, &[], 0)?, &fields[1..]),
+            count => return Err(CronError::FieldCount(count)),
+        };
+
+        let mut days_of_week = parse_field("day of week", fields[4], 0, 7, &WEEKDAY_NAMES, 0)?;
+        if has(days_of_week, 7) {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        let any = |field: &str| field.starts_with('*') || field == "?";
+
+        let schedule = CronSchedule {
+            expression: expression.to_owned(),
+            seconds,
+            minutes: parse_field("minute", fields[0], 0, 59, &[], 0)?,
+            hours: parse_field("hour", fields[1], 0, 23, &[], 0)?,
+            days_of_month: parse_field("day of month", fields[2], 1, 31, &[], 0)?,
+            months: parse_field("month", fields[3], 1, 12, &MONTH_NAMES, 1)?,
+            days_of_week,
+            any_day_of_month: any(fields[2]),
+            any_day_of_week: any(fields[4]),
+        };
+
+        // Day of week schedules always fire; day of month schedules need a month long enough (February has 29 days in leap years)
+        if schedule.any_day_of_week && !(1..=12).any(|month| has(schedule.months, month) && (1..=days_in_month(2000, month)).any(|day| has(schedule.days_of_month, day))) {
+            return Err(CronError::NeverFires)
+        }
+
+        Ok(schedule)
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = has(self.days_of_month, date.day());
+        let day_of_week = has(self.days_of_week, date.weekday().num_days_from_sunday());
+        if self.any_day_of_month || self.any_day_of_week {
+            day_of_month && day_of_week
+        } else {
+            day_of_month || day_of_week
+        }
+    }
+
+    fn resolve<Tz: TimeZone>(tz: &Tz, time: &NaiveDateTime) -> Option<DateTime<Tz>> {
+        match tz.from_local_datetime(time) {
+            LocalResult::Single(time) => Some(time),
+            // Fire only once when clock is turned back
+            LocalResult::Ambiguous(earliest, _) => Some(earliest),
+            // Local time skipped when clock is turned forward
+            LocalResult::None => None,
+        }
+    }
+
+    /// Returns first firing time strictly after given time, in its time zone.
+    pub fn next_after<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = time.timezone();
+        let mut t = time.naive_local().with_nanosecond(0)? + chrono::Duration::seconds(1);
+        let end_year = t.year() + SEARCH_YEARS;
+
+        while t.year() <= end_year {
+            let date = t.date();
+            if !has(self.months, t.month()) {
+                let (year, month) = if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(date) {
+                t = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !has(self.hours, t.hour()) {
+                t = date.and_hms_opt(t.hour(), 0, 0)? + chrono::Duration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t = date.and_hms_opt(t.hour(), t.minute(), 0)? + chrono::Duration::minutes(1);
+            } else if !has(self.seconds, t.second()) {
+                t += chrono::Duration::seconds(1);
+            } else {
+                match Self::resolve(&tz, &t) {
+                    Some(next) if next > *time => return Some(next),
+                    _ => t += chrono::Duration::seconds(1),
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns last firing time strictly before given time, in its time zone.
+    pub fn prev_before<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = time.timezone();
+        let naive = time.naive_local();
+        let mut t = naive.with_nanosecond(0)?;
+        if t == naive {
+            t -= chrono::Duration::seconds(1);
+        }
+        let end_year = t.year() - SEARCH_YEARS;
+
+        while t.year() >= end_year {
+            let date = t.date();
+            if !has(self.months, t.month()) {
+                t = NaiveDate::from_ymd_opt(t.year(), t.month(), 1)?.and_hms_opt(0, 0, 0)? - chrono::Duration::seconds(1);
+            } else if !self.matches_day(date) {
+                t = date.and_hms_opt(0, 0, 0)? - chrono::Duration::seconds(1);
+            } else if !has(self.hours, t.hour()) {
+                t = date.and_hms_opt(t.hour(), 0, 0)? - chrono::Duration::seconds(1);
+            } else if !has(self.minutes, t.minute()) {
+                t = date.and_hms_opt(t.hour(), t.minute(), 0)? - chrono::Duration::seconds(1);
+            } else if !has(self.seconds, t.second()) {
+                t -= chrono::Duration::seconds(1);
+            } else {
+                match Self::resolve(&tz, &t) {
+                    Some(prev) if prev < *time => return Some(prev),
+                    _ => t -= chrono::Duration::seconds(1),
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Iterates over firing times after given time.
+    pub fn upcoming<'a, Tz: TimeZone + 'a>(&'a self, after: DateTime<Tz>) -> impl Iterator<Item = DateTime<Tz>> + 'a {
+        std::iter::successors(self.next_after(&after), move |time| self.next_after(time))
+    }
+
+    /// Blocks until next firing time in given time zone and returns it.
+    ///
+    /// Waiting stops early when interrupt flag is set (e.g. by `signal_flag::register`).
+    pub fn wait_next<Tz: TimeZone>(&self, tz: &Tz, interrupt: Option<&AtomicBool>) -> Result<DateTime<Tz>, Interrupted> {
+        let next = self.next_after(&Utc::now().with_timezone(tz)).expect("cron schedule has no next firing time");
+        loop {
+            let remaining = next.clone().signed_duration_since(Utc::now()).to_std().unwrap_or(Duration::ZERO);
+            if remaining.is_zero() {
+                return Ok(next)
+            }
+            sleep_until_interruptible(Instant::now() + remaining.min(MAX_SLEEP), interrupt)?;
+        }
+    }
+
+    /// Runs job at each firing time in given time zone until interrupt flag is set or the job fails.
+    ///
+    /// The job is called with the scheduled time. Firings that happen while the job is still running are skipped.
+    pub fn run<Tz: TimeZone, E>(&self, tz: &Tz, interrupt: &AtomicBool, mut job: impl FnMut(DateTime<Tz>) -> Result<(), E>) -> Result<(), E> {
+        while let Ok(time) = self.wait_next(tz, Some(interrupt)) {
+            job(time)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = CronError;
+
+    fn from_str(s: &str) -> Result<CronSchedule, CronError> {
+        CronSchedule::parse(s)
+    }
+}
+
+impl Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    #[test]
+    fn cron_parse() {
+        assert!(CronSchedule::parse("0 2 * * MON-FRI").is_ok());
+        assert!(CronSchedule::parse("30 */5 * * * *").is_ok());
+        assert!(CronSchedule::parse("0 9 1,15 jan,JUL ?").is_ok());
+        assert!(CronSchedule::parse("@daily").is_ok());
+        assert_eq!(CronSchedule::parse("0 2 * *").unwrap_err(), CronError::FieldCount(4));
+        assert_eq!(CronSchedule::parse("0 24 * * *").unwrap_err(), CronError::InvalidField { field: "hour", value: "24".to_owned() });
+        assert_eq!(CronSchedule::parse("*/0 * * * *").unwrap_err(), CronError::InvalidField { field: "minute", value: "*/0".to_owned() });
+        assert_eq!(CronSchedule::parse("0 0 * * FUN").unwrap_err(), CronError::InvalidField { field: "day of week", value: "FUN".to_owned() });
+        assert_eq!(CronSchedule::parse("@reboot").unwrap_err(), CronError::UnknownMacro("@reboot".to_owned()));
+        assert_eq!(CronSchedule::parse("0 0 30 2 *").unwrap_err(), CronError::NeverFires);
+        assert_eq!(CronSchedule::parse(" 0 2 * * 1-5 ").unwrap().to_string(), "0 2 * * 1-5");
+    }
+
+    #[test]
+    fn cron_next_prev() {
+        let utc = |y, m, d, h, min, s| Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap();
+        // Friday
+        let now = utc(2020, 10, 9, 12, 30, 10);
+
+        let weekdays = CronSchedule::parse("0 2 * * MON-FRI").unwrap();
+        assert_eq!(weekdays.next_after(&now), Some(utc(2020, 10, 12, 2, 0, 0)));
+        assert_eq!(weekdays.prev_before(&now), Some(utc(2020, 10, 9, 2, 0, 0)));
+        assert_eq!(weekdays.next_after(&utc(2020, 10, 12, 2, 0, 0)), Some(utc(2020, 10, 13, 2, 0, 0)));
+        assert_eq!(weekdays.prev_before(&utc(2020, 10, 12, 2, 0, 0)), Some(utc(2020, 10, 9, 2, 0, 0)));
+
+        let quarter = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(quarter.next_after(&now), Some(utc(2020, 10, 9, 12, 45, 0)));
+        assert_eq!(quarter.prev_before(&now), Some(utc(2020, 10, 9, 12, 30, 0)));
+        assert_eq!(quarter.upcoming(now).take(3).collect::<Vec<_>>(), [utc(2020, 10, 9, 12, 45, 0), utc(2020, 10, 9, 13, 0, 0), utc(2020, 10, 9, 13, 15, 0)]);
+
+        let seconds = CronSchedule::parse("30 */5 * * * *").unwrap();
+        assert_eq!(seconds.next_after(&now), Some(utc(2020, 10, 9, 12, 30, 30)));
+
+        let leap = CronSchedule::parse("0 0 29 feb *").unwrap();
+        assert_eq!(leap.next_after(&now), Some(utc(2024, 2, 29, 0, 0, 0)));
+        assert_eq!(leap.prev_before(&now), Some(utc(2020, 2, 29, 0, 0, 0)));
+
+        // Stepped star fields still restrict the days
+        let every_other_day = CronSchedule::parse("0 0 */2 * *").unwrap();
+        assert_eq!(every_other_day.upcoming(now).take(3).collect::<Vec<_>>(), [utc(2020, 10, 11, 0, 0, 0), utc(2020, 10, 13, 0, 0, 0), utc(2020, 10, 15, 0, 0, 0)]);
+        assert_eq!(every_other_day.prev_before(&now), Some(utc(2020, 10, 9, 0, 0, 0)));
+        let every_third_weekday = CronSchedule::parse("0 0 * * */3").unwrap();
+        // Sunday, Wednesday and Saturday
+        assert_eq!(every_third_weekday.upcoming(now).take(3).collect::<Vec<_>>(), [utc(2020, 10, 10, 0, 0, 0), utc(2020, 10, 11, 0, 0, 0), utc(2020, 10, 14, 0, 0, 0)]);
+        // Star in one field makes both fields required: odd days that are Mondays
+        let odd_mondays = CronSchedule::parse("0 0 */2 * MON").unwrap();
+        assert_eq!(odd_mondays.next_after(&now), Some(utc(2020, 10, 19, 0, 0, 0)));
+
+        // Either day of month or day of week matches: 13th or Friday
+        let friday13 = CronSchedule::parse("0 0 13 * 5").unwrap();
+        assert_eq!(friday13.next_after(&now), Some(utc(2020, 10, 13, 0, 0, 0)));
+        assert_eq!(friday13.prev_before(&now), Some(utc(2020, 10, 9, 0, 0, 0)));
+
+        let sunday = CronSchedule::parse("@weekly").unwrap();
+        assert_eq!(sunday.next_after(&now), Some(utc(2020, 10, 11, 0, 0, 0)));
+        assert_eq!(CronSchedule::parse("0 0 * * 7").unwrap().next_after(&now), Some(utc(2020, 10, 11, 0, 0, 0)));
+
+        let cest = FixedOffset::east_opt(2 * 3600).unwrap();
+        let local = now.with_timezone(&cest);
+        let next = weekdays.next_after(&local).unwrap();
+        assert_eq!(next, cest.with_ymd_and_hms(2020, 10, 12, 2, 0, 0).unwrap());
+        assert_eq!(next.with_timezone(&Utc), utc(2020, 10, 12, 0, 0, 0));
+    }
+
+    #[test]
+    fn cron_wait_interrupted() {
+        let interrupt = AtomicBool::new(true);
+        let schedule = CronSchedule::parse("0 0 1 1 *").unwrap();
+        assert_eq!(schedule.wait_next(&Utc, Some(&interrupt)), Err(Interrupted));
+        assert!(schedule.run(&Utc, &interrupt, |_| -> Result<(), ()> { panic!("should not run") }).is_ok());
+    }
+}
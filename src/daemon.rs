@@ -0,0 +1,268 @@
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::fmt::{self, Display};
+use std::error::Error;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+use crate::lock::{InstanceLock, LockError, LockWait};
+
+#[derive(Debug)]
+pub enum DaemonError {
+    ForkError(io::Error),
+    SetsidError(io::Error),
+    ChdirError(PathBuf, io::Error),
+    RedirectError(PathBuf, io::Error),
+    PidFileError(LockError),
+}
+
+impl From<LockError> for DaemonError {
+    fn from(err: LockError) -> DaemonError {
+        DaemonError::PidFileError(err)
+    }
+}
+
+impl Display for DaemonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DaemonError::ForkError(_) => write!(f, "failed to fork daemon process"),
+            DaemonError::SetsidError(_) => write!(f, "failed to create new session for daemon process"),
+            DaemonError::ChdirError(path, _) => write!(f, "failed to change daemon working directory to {:?}", path),
+            DaemonError::RedirectError(path, _) => write!(f, "failed to redirect standard I/O to {:?}", path),
+            DaemonError::PidFileError(_) => write!(f, "failed to create daemon PID file"),
+        }
+    }
+}
+
+impl Error for DaemonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DaemonError::ForkError(err) => Some(err),
+            DaemonError::SetsidError(err) => Some(err),
+            DaemonError::ChdirError(_, err) => Some(err),
+            DaemonError::RedirectError(_, err) => Some(err),
+            DaemonError::PidFileError(err) => Some(err),
+        }
+    }
+}
+
+/// Configuration of the process running as a service.
+///
+/// By default the process stays in foreground (as expected by systemd and other supervisors) and only the PID file
+/// and standard I/O redirection are set up if configured.
+#[derive(Debug, Default)]
+pub struct Daemon {
+    detach: bool,
+    pid_file: Option<PathBuf>,
+    log_file: Option<PathBuf>,
+    working_dir: Option<PathBuf>,
+}
+
+fn fork() -> Result<libc::pid_t, DaemonError> {
+    match unsafe { libc::fork() } {
+        -1 => Err(DaemonError::ForkError(io::Error::last_os_error())),
+        pid => Ok(pid),
+    }
+}
+
+fn redirect(file: &File, fd: libc::c_int) -> Result<(), io::Error> {
+    if unsafe { libc::dup2(file.as_raw_fd(), fd) } == -1 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(())
+}
+
+impl Daemon {
+    /// Creates configuration of a service staying in foreground.
+    pub fn new() -> Daemon {
+        Daemon::default()
+    }
+
+    /// Detaches from the terminal with double-fork; the original process exits.
+    ///
+    /// Working directory of detached process is changed to `/` unless set with [Daemon::working_dir].
+    pub fn detach(mut self, detach: bool) -> Daemon {
+        self.detach = detach;
+        self
+    }
+
+    /// Writes PID of the daemon to given file, locking it so only one instance can run.
+    pub fn pid_file(mut self, path: impl Into<PathBuf>) -> Daemon {
+        self.pid_file = Some(path.into());
+        self
+    }
+
+    /// Redirects stdout and stderr (and thus logger output) to given file, appending to it.
+    ///
+    /// Detached process without log file has its stdout and stderr redirected to `/dev/null`.
+    pub fn log_file(mut self, path: impl Into<PathBuf>) -> Daemon {
+        self.log_file = Some(path.into());
+        self
+    }
+
+    /// Changes working directory of the daemon.
+    pub fn working_dir(mut self, path: impl Into<PathBuf>) -> Daemon {
+        self.working_dir = Some(path.into());
+        self
+    }
+
+    /// Starts the daemon returning PID file lock if PID file was configured; it needs to be kept for the lifetime of the daemon.
+    ///
+    /// This should be called early, before any threads are spawned, as only the calling thread survives the fork.
+    pub fn start(self) -> Result<Option<InstanceLock>, DaemonError> {
+        // Open files before detaching so relative paths are resolved against original working directory
+        let log_file = self.log_file.as_ref()
+            .map(|path| OpenOptions::new().create(true).append(true).open(path).map_err(|err| DaemonError::RedirectError(path.clone(), err)))
+            .transpose()?;
+        // Lock PID file before detaching so that failure is reported by the launching process; the lock is inherited by the daemon
+        let mut pid_lock = self.pid_file.as_ref()
+            .map(|path| env::current_dir().map(|dir| dir.join(path)).map_err(|err| LockError::IoError(path.clone(), err)))
+            .transpose()?
+            .map(|path| InstanceLock::acquire(path, LockWait::NoWait))
+            .transpose()?;
+
+        if self.detach {
+            if fork()? != 0 {
+                // Do not run destructors: the lock file must not be cleared
+                unsafe { libc::_exit(0) }
+            }
+            if unsafe { libc::setsid() } == -1 {
+                return Err(DaemonError::SetsidError(io::Error::last_os_error()))
+            }
+            // Session leader exits so the daemon can never acquire controlling terminal
+            if fork()? != 0 {
+                unsafe { libc::_exit(0) }
+            }
+        }
+
+        if let Some(dir) = self.working_dir.as_deref().or_else(|| Some(Path::new("/")).filter(|_| self.detach)) {
+            env::set_current_dir(dir).map_err(|err| DaemonError::ChdirError(dir.to_owned(), err))?;
+        }
+
+        if self.detach || log_file.is_some() {
+            let null_path = Path::new("/dev/null");
+            let redirect_error = |path: &Path| { let path = path.to_owned(); move |err| DaemonError::RedirectError(path, err) };
+            let null = OpenOptions::new().read(true).write(true).open(null_path).map_err(redirect_error(null_path))?;
+            redirect(&null, libc::STDIN_FILENO).map_err(redirect_error(null_path))?;
+
+            let (output, output_path) = match (&log_file, &self.log_file) {
+                (Some(file), Some(path)) => (file, path.as_path()),
+                _ => (&null, null_path),
+            };
+            redirect(output, libc::STDOUT_FILENO).map_err(redirect_error(output_path))?;
+            redirect(output, libc::STDERR_FILENO).map_err(redirect_error(output_path))?;
+        }
+
+        if self.detach {
+            if let Some(lock) = pid_lock.as_mut() {
+                lock.update_pid()?;
+            }
+        }
+
+        Ok(pid_lock)
+    }
+}
+
+/// Sends notification to service manager (systemd) over socket given by `$NOTIFY_SOCKET`.
+///
+/// State is newline separated list of assignments like `READY=1` or `STATUS=Processing`.
+/// Returns false if the process is not supervised (`$NOTIFY_SOCKET` is not set).
+pub fn sd_notify(state: &str) -> Result<bool, io::Error> {
+    let socket_path = match env::var_os("NOTIFY_SOCKET") {
+        Some(path) if !path.is_empty() => path,
+        _ => return Ok(false),
+    };
+    let socket = UnixDatagram::unbound()?;
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::net::SocketAddr;
+
+        if let Some(name) = socket_path.as_bytes().strip_prefix(b"@") {
+            socket.send_to_addr(state.as_bytes(), &SocketAddr::from_abstract_name(name)?)?;
+            return Ok(true)
+        }
+    }
+
+    socket.send_to(state.as_bytes(), socket_path)?;
+    Ok(true)
+}
+
+/// Notifies service manager that the service finished starting up.
+pub fn notify_ready() -> Result<bool, io::Error> {
+    sd_notify("READY=1")
+}
+
+/// Notifies service manager that the service is shutting down.
+pub fn notify_stopping() -> Result<bool, io::Error> {
+    sd_notify("STOPPING=1")
+}
+
+/// Notifies service manager that the service is alive; needs to be sent within [watchdog_interval].
+pub fn notify_watchdog() -> Result<bool, io::Error> {
+    sd_notify("WATCHDOG=1")
+}
+
+/// Sends free-form status of the service to service manager.
+pub fn notify_status(status: &str) -> Result<bool, io::Error> {
+    sd_notify(&format!("STATUS={}", status.replace('\n', " ")))
+}
+
+/// Returns watchdog timeout configured by service manager for this process (`$WATCHDOG_USEC`).
+///
+/// Watchdog notifications should be sent at half of this interval.
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None
+        }
+    }
+    env::var("WATCHDOG_USEC").ok()?.parse().ok().filter(|usec| *usec > 0).map(Duration::from_micros)
+}
+
+#[cfg(all(test, feature = "tempfile"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daemon_pid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.pid");
+
+        let lock = Daemon::new().pid_file(&path).start().unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), format!("{}\n", std::process::id()));
+        assert!(matches!(Daemon::new().pid_file(&path).start(), Err(DaemonError::PidFileError(LockError::Locked { .. }))));
+        lock.release().unwrap();
+    }
+
+    #[test]
+    fn sd_notify_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify.sock");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut buf = [0; 64];
+        let mut receive = || {
+            let len = socket.recv(&mut buf).unwrap();
+            String::from_utf8(buf[..len].to_vec()).unwrap()
+        };
+
+        env::set_var("NOTIFY_SOCKET", &path);
+        assert!(notify_ready().unwrap());
+        assert_eq!(receive(), "READY=1");
+        notify_watchdog().unwrap();
+        assert_eq!(receive(), "WATCHDOG=1");
+        notify_status("working\nhard").unwrap();
+        assert_eq!(receive(), "STATUS=working hard");
+        notify_stopping().unwrap();
+        assert_eq!(receive(), "STOPPING=1");
+
+        env::remove_var("NOTIFY_SOCKET");
+        assert!(!notify_ready().unwrap());
+    }
+}
@@ -4,16 +4,40 @@ use std::io::{self, BufReader};
 use std::path::Path;
 use std::fmt::{self, Display};
 use std::error::Error;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+#[cfg(feature = "serde")]
+use std::borrow::Cow;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-pub use sha2::{Digest as DigestTrait, Sha256};
+pub use sha2::{Digest as DigestTrait, Sha224, Sha256, Sha384, Sha512};
 use sha2::digest::generic_array::GenericArray;
 use hex::{self, FromHexError};
 use digest::OutputSizeUser;
+use digest::core_api::BlockSizeUser;
+use hmac::{Mac, SimpleHmac};
+use subtle::ConstantTimeEq;
+use data_encoding::{DecodeError, DecodeKind, BASE32, BASE64};
+
+/// Alphabet of base32 encoding used by Nix.
+const NIX_BASE32_ALPHABET: &[u8; 32] = b"0123456789abcdfghijklmnpqrsvwxyz";
+
+/// Multihash code of SHA2-256 hash function.
+const MULTIHASH_SHA2_256: u64 = 0x12;
 
 #[derive(Debug)]
 pub enum DigestError {
     FromHexError(FromHexError),
+    FromBase64Error(DecodeError),
+    FromBase32Error(DecodeError),
+    UnsupportedAlgorithm(String),
+    InvalidMultihash,
+    UnsupportedMultihash(u64),
     LengthMissmatch { got: usize , expected: usize },
+    VerificationFailed,
+    DigestMissmatch { got: Digest, expected: Digest },
 }
 
 impl From<FromHexError> for DigestError {
@@ -26,7 +50,14 @@ impl Display for DigestError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             DigestError::FromHexError(_) => write!(f, "error converting hex string to digest"),
+            DigestError::FromBase64Error(_) => write!(f, "error converting base64 string to digest"),
+            DigestError::FromBase32Error(_) => write!(f, "error converting base32 string to digest"),
+            DigestError::UnsupportedAlgorithm(algorithm) => write!(f, "unsupported digest algorithm {:?}", algorithm),
+            DigestError::InvalidMultihash => write!(f, "invalid multihash encoding"),
+            DigestError::UnsupportedMultihash(code) => write!(f, "unsupported multihash function code 0x{:x}", code),
             DigestError::LengthMissmatch { got, expected } => write!(f, "digest length missmmatch, got {} bytes, expected {} bytes", got, expected),
+            DigestError::VerificationFailed => write!(f, "digest verification failed"),
+            DigestError::DigestMissmatch { got, expected } => write!(f, "digest missmatch, got {}, expected {}", got, expected),
         }
     }
 }
@@ -35,13 +66,23 @@ impl Error for DigestError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             DigestError::FromHexError(err) => Some(err),
+            DigestError::FromBase64Error(err) => Some(err),
+            DigestError::FromBase32Error(err) => Some(err),
+            DigestError::UnsupportedAlgorithm(_) => None,
+            DigestError::InvalidMultihash => None,
+            DigestError::UnsupportedMultihash(_) => None,
             DigestError::LengthMissmatch { .. } => None,
+            DigestError::VerificationFailed => None,
+            DigestError::DigestMissmatch { .. } => None,
         }
     }
 }
 
 /// Represents SHA2-256 hash value
-#[derive(PartialEq, Eq, Clone)]
+///
+/// Comparison for equality is done in constant time.
+/// Digest can be parsed from hex string with [FromStr] and with `serde` feature enabled it is serialized as hex string.
+#[derive(Clone)]
 pub struct Digest(GenericArray<u8, <Sha256 as OutputSizeUser>::OutputSize>);
 
 impl Digest {
@@ -50,7 +91,7 @@ impl Digest {
         if value.len() != <Sha256 as OutputSizeUser>::output_size() {
             Err(DigestError::LengthMissmatch { got: value.len(), expected: <Sha256 as OutputSizeUser>::output_size() })
         } else {
-            Ok(Digest(GenericArray::clone_from_slice(&value)))
+            Ok(Digest(GenericArray::clone_from_slice(value)))
         }
     }
 
@@ -91,6 +132,121 @@ impl Digest {
         hex::encode(self.as_bytes())
     }
 
+    /// Create new Digest from give base64 (RFC 4648 with padding) encoded bytes as is.
+    pub fn from_base64(base64: &str) -> Result<Digest, DigestError> {
+        Digest::new(&BASE64.decode(base64.as_bytes()).map_err(DigestError::FromBase64Error)?)
+    }
+
+    /// Encode digest value as base64 (RFC 4648 with padding) string.
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.as_bytes())
+    }
+
+    /// Create new Digest from give base32 (RFC 4648 with padding) encoded bytes as is.
+    pub fn from_base32(base32: &str) -> Result<Digest, DigestError> {
+        Digest::new(&BASE32.decode(base32.as_bytes()).map_err(DigestError::FromBase32Error)?)
+    }
+
+    /// Encode digest value as base32 (RFC 4648 with padding) string.
+    pub fn to_base32(&self) -> String {
+        BASE32.encode(self.as_bytes())
+    }
+
+    /// Create new Digest from Subresource Integrity metadata (e.g. `sha256-LCa0a2j/xo/5m0U8HTBBNBNCLXBkg7+g+YpeiGJm564=`).
+    pub fn from_sri(sri: &str) -> Result<Digest, DigestError> {
+        match sri.split_once('-') {
+            Some(("sha256", base64)) => Digest::from_base64(base64),
+            Some((algorithm, _)) => Err(DigestError::UnsupportedAlgorithm(algorithm.to_owned())),
+            None => Err(DigestError::UnsupportedAlgorithm(sri.to_owned())),
+        }
+    }
+
+    /// Encode digest value as Subresource Integrity metadata (e.g. `sha256-LCa0a2j/xo/5m0U8HTBBNBNCLXBkg7+g+YpeiGJm564=`).
+    pub fn to_sri(&self) -> String {
+        format!("sha256-{}", self.to_base64())
+    }
+
+    /// Create new Digest from give base32 encoded bytes as used by Nix.
+    pub fn from_nix_base32(base32: &str) -> Result<Digest, DigestError> {
+        let len = <Sha256 as OutputSizeUser>::output_size();
+        if base32.len() != (len * 8 - 1) / 5 + 1 {
+            return Err(DigestError::FromBase32Error(DecodeError { position: base32.len().min((len * 8 - 1) / 5 + 1), kind: DecodeKind::Length }))
+        }
+
+        let mut bytes = vec![0u8; len];
+        for (position, c) in base32.bytes().enumerate() {
+            let value = NIX_BASE32_ALPHABET.iter().position(|a| *a == c)
+                .ok_or(DigestError::FromBase32Error(DecodeError { position, kind: DecodeKind::Symbol }))? as u16;
+
+            let n = base32.len() - 1 - position;
+            let b = n * 5;
+            let i = b / 8;
+            let j = b % 8;
+            let value = value << j;
+            bytes[i] |= value as u8;
+            let carry = (value >> 8) as u8;
+            if i + 1 < len {
+                bytes[i + 1] |= carry;
+            } else if carry != 0 {
+                return Err(DigestError::FromBase32Error(DecodeError { position, kind: DecodeKind::Trailing }))
+            }
+        }
+
+        Digest::new(&bytes)
+    }
+
+    /// Encode digest value as base32 string as used by Nix.
+    pub fn to_nix_base32(&self) -> String {
+        let bytes = self.as_bytes();
+        let len = (bytes.len() * 8 - 1) / 5 + 1;
+
+        (0..len).rev().map(|n| {
+            let b = n * 5;
+            let i = b / 8;
+            let j = b % 8;
+            let c = (bytes[i] as u16 >> j) | bytes.get(i + 1).map(|next| (*next as u16) << (8 - j)).unwrap_or(0);
+            NIX_BASE32_ALPHABET[(c & 0x1f) as usize] as char
+        }).collect()
+    }
+
+    /// Create new Digest from multihash encoded bytes.
+    ///
+    /// Only SHA2-256 (code `0x12`) multihash is supported.
+    pub fn from_multihash(multihash: &[u8]) -> Result<Digest, DigestError> {
+        fn read_varint(bytes: &mut &[u8]) -> Result<u64, DigestError> {
+            let mut value = 0u64;
+            for shift in (0..63).step_by(7) {
+                let (byte, rest) = bytes.split_first().ok_or(DigestError::InvalidMultihash)?;
+                *bytes = rest;
+                value |= ((byte & 0x7f) as u64) << shift;
+                if byte & 0x80 == 0 {
+                    return Ok(value)
+                }
+            }
+            Err(DigestError::InvalidMultihash)
+        }
+
+        let mut bytes = multihash;
+        let code = read_varint(&mut bytes)?;
+        if code != MULTIHASH_SHA2_256 {
+            return Err(DigestError::UnsupportedMultihash(code))
+        }
+
+        let len = read_varint(&mut bytes)? as usize;
+        if len != bytes.len() {
+            return Err(DigestError::LengthMissmatch { got: bytes.len(), expected: len })
+        }
+
+        Digest::new(bytes)
+    }
+
+    /// Encode digest value as multihash bytes.
+    pub fn to_multihash(&self) -> Vec<u8> {
+        let mut multihash = vec![MULTIHASH_SHA2_256 as u8, self.as_bytes().len() as u8];
+        multihash.extend_from_slice(self.as_bytes());
+        multihash
+    }
+
     /// Returns digest value as bytes.
     pub fn as_bytes(&self) -> &[u8] {
         self.0.as_slice()
@@ -102,6 +258,55 @@ impl Digest {
     }
 }
 
+impl PartialEq for Digest {
+    fn eq(&self, other: &Digest) -> bool {
+        self.as_bytes().ct_eq(other.as_bytes()).into()
+    }
+}
+
+impl Eq for Digest {}
+
+impl Hash for Digest {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.as_bytes().hash(state)
+    }
+}
+
+impl PartialOrd for Digest {
+    fn partial_cmp(&self, other: &Digest) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Digest {
+    fn cmp(&self, other: &Digest) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl FromStr for Digest {
+    type Err = DigestError;
+
+    fn from_str(hex: &str) -> Result<Digest, DigestError> {
+        Digest::from_hex(hex)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Digest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Digest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Digest, D::Error> {
+        let hex = <Cow<'de, str>>::deserialize(deserializer)?;
+        Digest::from_hex(&hex).map_err(de::Error::custom)
+    }
+}
+
 impl Display for Digest {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:X}", self.0)
@@ -116,6 +321,252 @@ impl fmt::Debug for Digest {
     }
 }
 
+/// Represents HMAC keyed digest value calculated with SHA-2 family hash function (SHA2-256 by default).
+///
+/// Comparison of keyed digests is done in constant time.
+#[derive(Clone)]
+pub struct KeyedDigest<H: OutputSizeUser = Sha256>(GenericArray<u8, H::OutputSize>);
+
+impl<H> KeyedDigest<H> where H: DigestTrait + BlockSizeUser + Clone {
+    fn mac(key: &[u8]) -> SimpleHmac<H> {
+        <SimpleHmac<H> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length")
+    }
+
+    /// Create new KeyedDigest from give bytes as is.
+    pub fn new(value: &[u8]) -> Result<KeyedDigest<H>, DigestError> {
+        if value.len() != <H as OutputSizeUser>::output_size() {
+            Err(DigestError::LengthMissmatch { got: value.len(), expected: <H as OutputSizeUser>::output_size() })
+        } else {
+            Ok(KeyedDigest(GenericArray::clone_from_slice(value)))
+        }
+    }
+
+    /// Create new KeyedDigest from give hex encoded bytes as is.
+    pub fn from_hex(hex: &str) -> Result<KeyedDigest<H>, DigestError> {
+        KeyedDigest::new(&hex::decode(hex)?)
+    }
+
+    /// Create new KeyedDigest from give base64 encoded bytes as is.
+    pub fn from_base64(base64: &str) -> Result<KeyedDigest<H>, DigestError> {
+        KeyedDigest::new(&BASE64.decode(base64.as_bytes()).map_err(DigestError::FromBase64Error)?)
+    }
+
+    /// Calculate keyed digest from content read from a reader.
+    pub fn from_reader<R: Read>(key: &[u8], reader: &mut R) -> Result<KeyedDigest<H>, io::Error> {
+        let mut mac = Self::mac(key);
+        let mut buf = [0; 8 * 1024];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => mac.update(&buf[..len]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(KeyedDigest(mac.finalize().into_bytes()))
+    }
+
+    /// Calculate keyed digest from a stream of byte buffers.
+    pub fn from_buffers<S: AsRef<[u8]>>(key: &[u8], buffers: impl IntoIterator<Item = S, IntoIter = impl Iterator<Item = S>>) -> KeyedDigest<H> {
+        let mut mac = Self::mac(key);
+        for buffer in buffers {
+            mac.update(buffer.as_ref());
+        }
+        KeyedDigest(mac.finalize().into_bytes())
+    }
+
+    /// Calculate keyed digest from bytes.
+    pub fn from_bytes<S: AsRef<[u8]>>(key: &[u8], bytes: S) -> KeyedDigest<H> {
+        KeyedDigest::from_buffers(key, Some(bytes))
+    }
+
+    /// Encode keyed digest value as hex string.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.as_bytes())
+    }
+
+    /// Encode keyed digest value as base64 string.
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.as_bytes())
+    }
+
+    /// Returns keyed digest value as bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    /// Verifies in constant time that given bytes are equal to this keyed digest value.
+    pub fn verify(&self, value: &[u8]) -> Result<(), DigestError> {
+        if value.len() != <H as OutputSizeUser>::output_size() {
+            return Err(DigestError::LengthMissmatch { got: value.len(), expected: <H as OutputSizeUser>::output_size() })
+        }
+        if bool::from(self.as_bytes().ct_eq(value)) {
+            Ok(())
+        } else {
+            Err(DigestError::VerificationFailed)
+        }
+    }
+
+    /// Verifies in constant time that given hex encoded bytes are equal to this keyed digest value.
+    pub fn verify_hex(&self, hex: &str) -> Result<(), DigestError> {
+        self.verify(&hex::decode(hex)?)
+    }
+
+    /// Verifies in constant time that given base64 encoded bytes are equal to this keyed digest value.
+    pub fn verify_base64(&self, base64: &str) -> Result<(), DigestError> {
+        self.verify(&BASE64.decode(base64.as_bytes()).map_err(DigestError::FromBase64Error)?)
+    }
+}
+
+impl<H: OutputSizeUser> PartialEq for KeyedDigest<H> {
+    fn eq(&self, other: &KeyedDigest<H>) -> bool {
+        self.0.as_slice().ct_eq(other.0.as_slice()).into()
+    }
+}
+
+impl<H: OutputSizeUser> Eq for KeyedDigest<H> {}
+
+impl<H: OutputSizeUser> Display for KeyedDigest<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode_upper(self.0.as_slice()))
+    }
+}
+
+impl<H: OutputSizeUser> fmt::Debug for KeyedDigest<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("KeyedDigest")
+            .field(&format_args!("{}", hex::encode_upper(self.0.as_slice())))
+            .finish()
+    }
+}
+
+impl From<KeyedDigest<Sha256>> for Digest {
+    fn from(keyed: KeyedDigest<Sha256>) -> Digest {
+        Digest(keyed.0)
+    }
+}
+
+/// Reader adapter that calculates digest of all data read through it.
+///
+/// If expected digest is given, reading will fail with [io::ErrorKind::InvalidData] error
+/// wrapping [DigestError::DigestMissmatch] on end of stream if calculated digest does not match.
+pub struct HashingReader<R> {
+    inner: R,
+    digest: Sha256,
+    expected: Option<Digest>,
+}
+
+impl<R: Read> HashingReader<R> {
+    /// Wraps reader to calculate digest of all data read through it.
+    pub fn new(inner: R) -> HashingReader<R> {
+        HashingReader {
+            inner,
+            digest: Sha256::new(),
+            expected: None,
+        }
+    }
+
+    /// Wraps reader to calculate digest of all data read through it and verify it against expected digest on end of stream.
+    pub fn with_expected(inner: R, expected: Digest) -> HashingReader<R> {
+        HashingReader {
+            inner,
+            digest: Sha256::new(),
+            expected: Some(expected),
+        }
+    }
+
+    /// Returns digest of data read so far.
+    pub fn digest(&self) -> Digest {
+        Digest(self.digest.clone().finalize())
+    }
+
+    /// Unwraps inner reader and returns it together with digest of data read so far.
+    pub fn into_inner(self) -> (R, Digest) {
+        (self.inner, Digest(self.digest.finalize()))
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+
+        if len == 0 && !buf.is_empty() {
+            if let Some(expected) = self.expected.as_ref() {
+                let got = self.digest();
+                if &got != expected {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, DigestError::DigestMissmatch { got, expected: expected.clone() }))
+                }
+            }
+        }
+
+        self.digest.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+/// Writer adapter that calculates digest of all data written through it.
+///
+/// If expected digest is given, [HashingWriter::finish] will fail with [io::ErrorKind::InvalidData] error
+/// wrapping [DigestError::DigestMissmatch] if calculated digest does not match.
+pub struct HashingWriter<W> {
+    inner: W,
+    digest: Sha256,
+    expected: Option<Digest>,
+}
+
+impl<W: Write> HashingWriter<W> {
+    /// Wraps writer to calculate digest of all data written through it.
+    pub fn new(inner: W) -> HashingWriter<W> {
+        HashingWriter {
+            inner,
+            digest: Sha256::new(),
+            expected: None,
+        }
+    }
+
+    /// Wraps writer to calculate digest of all data written through it and verify it against expected digest on finish.
+    pub fn with_expected(inner: W, expected: Digest) -> HashingWriter<W> {
+        HashingWriter {
+            inner,
+            digest: Sha256::new(),
+            expected: Some(expected),
+        }
+    }
+
+    /// Returns digest of data written so far.
+    pub fn digest(&self) -> Digest {
+        Digest(self.digest.clone().finalize())
+    }
+
+    /// Flushes inner writer and returns it together with digest of all written data.
+    ///
+    /// Fails if expected digest was given and it does not match the digest of written data.
+    pub fn finish(mut self) -> Result<(W, Digest), io::Error> {
+        self.inner.flush()?;
+        let got = Digest(self.digest.finalize());
+
+        if let Some(expected) = self.expected {
+            if got != expected {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, DigestError::DigestMissmatch { got, expected }))
+            }
+        }
+
+        Ok((self.inner, got))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.digest.update(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
 #[derive(Debug)]
 pub enum FileDigestError {
     IoError(io::Error),
@@ -156,13 +607,140 @@ pub fn hex_digest_file(path: impl AsRef<Path>) -> Result<String, FileDigestError
     Ok(Digest::from_file(path)?.to_hex())
 }
 
+/// Calculates HMAC-SHA2-256 keyed hash from list of strings and returns hex representation.
+pub fn hex_hmac<S: AsRef<[u8]>>(
+    key: &[u8],
+    parts: impl IntoIterator<Item = S, IntoIter = impl Iterator<Item = S>>,
+) -> String {
+    KeyedDigest::<Sha256>::from_buffers(key, parts).to_hex()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
+    use std::collections::HashSet;
 
     #[test]
     fn test_hex_digest() {
-        assert_eq!(hex_digest(&["foo", "bar"]), "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2".to_owned());
-        assert_eq!(hex_digest(&[b"foo", b"bar"]), "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2".to_owned());
+        assert_eq!(hex_digest(["foo", "bar"]), "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2".to_owned());
+        assert_eq!(hex_digest([b"foo", b"bar"]), "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2".to_owned());
+    }
+
+    #[test]
+    fn test_hex_hmac() {
+        // RFC 4231 test case 2
+        assert_eq!(hex_hmac(b"Jefe", ["what do ya want ", "for nothing?"]), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843".to_owned());
+    }
+
+    #[test]
+    fn test_keyed_digest_verify() {
+        let keyed = KeyedDigest::<Sha512>::from_bytes(b"secret", "payload");
+
+        assert!(keyed.verify_base64(&keyed.to_base64()).is_ok());
+        assert_matches::assert_matches!(keyed.verify_hex(&KeyedDigest::<Sha512>::from_bytes(b"other", "payload").to_hex()), Err(DigestError::VerificationFailed));
+        assert_matches::assert_matches!(keyed.verify_hex("abcd"), Err(DigestError::LengthMissmatch { got: 2, expected: 64 }));
+        assert_matches::assert_matches!(keyed.verify_base64("!"), Err(DigestError::FromBase64Error(_)));
+    }
+
+    #[test]
+    fn test_hashing_reader() {
+        let expected = Digest::from_bytes("foobar");
+        let mut reader = HashingReader::with_expected(Cursor::new("foobar"), expected.clone());
+        let mut out = Vec::new();
+        io::copy(&mut reader, &mut out).unwrap();
+
+        assert_eq!(out, b"foobar");
+        assert_eq!(reader.into_inner().1, expected);
+
+        let mut reader = HashingReader::with_expected(Cursor::new("foobaz"), expected);
+        let err = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_matches::assert_matches!(err.get_ref().and_then(|err| err.downcast_ref::<DigestError>()), Some(DigestError::DigestMissmatch { .. }));
+    }
+
+    #[test]
+    fn test_hashing_writer() {
+        let mut writer = HashingWriter::with_expected(Vec::new(), Digest::from_bytes("foobar"));
+        writer.write_all(b"foo").unwrap();
+        writer.write_all(b"bar").unwrap();
+
+        let (out, digest) = writer.finish().unwrap();
+        assert_eq!(out, b"foobar");
+        assert_eq!(digest.to_hex(), hex_digest(["foobar"]));
+
+        let mut writer = HashingWriter::with_expected(io::sink(), Digest::from_bytes("foobar"));
+        writer.write_all(b"foo").unwrap();
+        assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_digest_from_str() {
+        let digest: Digest = "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2".parse().unwrap();
+        assert_eq!(digest, Digest::from_bytes("foobar"));
+        assert_matches::assert_matches!("c3ab".parse::<Digest>(), Err(DigestError::LengthMissmatch { got: 2, expected: 32 }));
+        assert_matches::assert_matches!("foo".parse::<Digest>(), Err(DigestError::FromHexError(_)));
+    }
+
+    #[test]
+    fn test_digest_hash_and_ord() {
+        let mut digests = [Digest::from_bytes("foo"), Digest::from_bytes("bar"), Digest::from_bytes("baz")];
+        digests.sort();
+        assert!(digests.windows(2).all(|pair| pair[0].to_hex() < pair[1].to_hex()));
+
+        let set: HashSet<Digest> = digests.iter().cloned().chain(digests.iter().cloned()).collect();
+        assert_eq!(set.len(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_digest_serde() {
+        use serde::de::value::{Error as DeError, StrDeserializer};
+        use serde::de::IntoDeserializer;
+
+        let deserializer: StrDeserializer<DeError> = "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2".into_deserializer();
+        assert_eq!(Digest::deserialize(deserializer).unwrap(), Digest::from_bytes("foobar"));
+
+        let deserializer: StrDeserializer<DeError> = "foo".into_deserializer();
+        assert!(Digest::deserialize(deserializer).is_err());
+    }
+
+    #[test]
+    fn test_digest_encodings() {
+        let digest = Digest::from_bytes("foobar");
+
+        assert_eq!(digest.to_base64(), "w6uP8Tcg6K2QR905Rms8iXTlksL6OD1KOWBxTK7wxPI=");
+        assert_eq!(Digest::from_base64(&digest.to_base64()).unwrap(), digest);
+        assert_eq!(Digest::from_base32(&digest.to_base32()).unwrap(), digest);
+        assert_eq!(digest.to_sri(), "sha256-w6uP8Tcg6K2QR905Rms8iXTlksL6OD1KOWBxTK7wxPI=");
+        assert_eq!(Digest::from_sri(&digest.to_sri()).unwrap(), digest);
+        assert_matches::assert_matches!(Digest::from_sri("sha512-w6uP"), Err(DigestError::UnsupportedAlgorithm(a)) if a == "sha512");
+        assert_matches::assert_matches!(Digest::from_base32("!!!!!!!!"), Err(DigestError::FromBase32Error(_)));
+    }
+
+    #[test]
+    fn test_digest_nix_base32() {
+        // nix-hash --type sha256 --to-base32 e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855
+        let digest = Digest::from_bytes("");
+        let nix = digest.to_nix_base32();
+
+        assert_eq!(nix, "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73");
+        assert_eq!(digest.to_sri(), "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=");
+        assert_eq!(Digest::from_nix_base32(&nix).unwrap(), digest);
+        assert_matches::assert_matches!(Digest::from_nix_base32(&nix[1..]), Err(DigestError::FromBase32Error(_)));
+        assert_matches::assert_matches!(Digest::from_nix_base32(&nix.replace('m', "e")), Err(DigestError::FromBase32Error(_)));
+    }
+
+    #[test]
+    fn test_digest_multihash() {
+        let digest = Digest::from_bytes("foobar");
+        let multihash = digest.to_multihash();
+
+        assert_eq!(&multihash[..2], &[0x12, 0x20]);
+        assert_eq!(Digest::from_multihash(&multihash).unwrap(), digest);
+        assert_matches::assert_matches!(Digest::from_multihash(&[0x13, 0x20]), Err(DigestError::UnsupportedMultihash(0x13)));
+        assert_matches::assert_matches!(Digest::from_multihash(&multihash[..10]), Err(DigestError::LengthMissmatch { got: 8, expected: 32 }));
+        assert_matches::assert_matches!(Digest::from_multihash(&[0x92]), Err(DigestError::InvalidMultihash));
     }
 }
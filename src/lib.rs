@@ -67,10 +67,13 @@ For convenience there are features defined that group several crates together:
   * [atty](https://docs.rs/atty) - A simple interface for querying atty
   * [zzz](https://docs.rs/zzz) - Fast progress bar with sane defaults
   * [term_size](https://docs.rs/term_size) - functions for determining terminal sizes and dimensions
-* `hashing` - digest calculations and hex encoding
+* `hashing` - digest calculations, HMAC and hex/base64 encoding
   * [hex](https://docs.rs/hex) - Encoding and decoding data into/from hexadecimal representation
   * [sha2](https://docs.rs/sha2) - Pure Rust implementation of the SHA-2 hash function family
   * [digest](https://docs.rs/digest) - Traits for cryptographic hash functions and message authentication codes
+  * [hmac](https://docs.rs/hmac) - Generic implementation of Hash-based Message Authentication Code (HMAC)
+  * [subtle](https://docs.rs/subtle) - Pure-Rust traits and utilities for constant-time cryptographic implementations
+  * [data-encoding](https://docs.rs/data-encoding) - Efficient and customizable data-encoding functions like base64, base32, and hex
 * `files` - file metadata and temporary files
   * [tempfile](https://docs.rs/tempfile) - A library for managing temporary files and directories
   * [filetime](https://docs.rs/filetime) - Platform-agnostic accessors of timestamps in File metadata
@@ -91,10 +94,13 @@ For convenience there are features defined that group several crates together:
   * [exec](https://docs.rs/exec) - Use the POSIX exec function to replace the running program with another
   * [mkargs](https://docs.rs/mkargs) - Build command arguments
   * [cradle](https://docs.rs/cradle) - Execute child processes with ease
+* `daemon` - running as a service (also enables `app`)
+  * [libc](https://docs.rs/libc) - Raw FFI bindings to platform libraries like libc
 
 Non-default features:
 
 * `backtrace` - enable backtraces for [problem::Problem] errors (also run your program with `RUST_BACKTRACE=1`)
+* `serde` - enable serialization of types like `Digest` with [serde](https://docs.rs/serde)
 
 For example you my include `cotton` like this in `Cargo.toml`:
 
@@ -160,11 +166,26 @@ impl From<ErrorContext<FileDigestError, PathBuf>> for FileResourceError {
 
 #[cfg(feature = "directories")]
 mod app_dir;
-#[cfg(all(feature = "hex", feature = "digest", feature = "sha2"))]
+#[cfg(all(feature = "hex", feature = "digest", feature = "sha2", feature = "hmac", feature = "subtle", feature = "data-encoding"))]
 mod hashing;
+#[cfg(all(feature = "directories", feature = "tempfile", feature = "filetime", feature = "hex", feature = "digest", feature = "sha2", feature = "hmac", feature = "subtle", feature = "data-encoding"))]
+mod blob_store;
+#[cfg(all(feature = "directories", feature = "tempfile", feature = "hex", feature = "digest", feature = "sha2", feature = "hmac", feature = "subtle", feature = "data-encoding"))]
+mod cache;
+#[cfg(all(target_family = "unix", feature = "libc", feature = "directories"))]
+mod lock;
+#[cfg(all(target_family = "unix", feature = "libc", feature = "directories", feature = "tempfile"))]
+mod state;
 #[cfg(feature = "chrono")]
 mod time;
+#[cfg(feature = "chrono")]
+mod cron;
 mod process;
+mod progress;
+#[cfg(all(target_family = "unix", feature = "libc", feature = "directories"))]
+mod daemon;
+#[cfg(all(target_family = "unix", feature = "signal-hook", feature = "libc"))]
+mod signals;
 
 // All used crates available for direct usage
 
@@ -227,6 +248,10 @@ pub use stderrlog;
 pub use sha2;
 #[cfg(feature = "digest")]
 pub use digest;
+#[cfg(feature = "hmac")]
+pub use hmac;
+#[cfg(feature = "subtle")]
+pub use subtle;
 
 // Shellout/processes
 #[cfg(feature = "shellwords")]
@@ -237,10 +262,14 @@ pub use exec;
 pub use mkargs;
 #[cfg(feature = "cradle")]
 pub use cradle;
+#[cfg(all(target_family = "unix", feature = "libc"))]
+pub use libc;
 
 // Strings
 #[cfg(feature = "hex")]
 pub use hex;
+#[cfg(feature = "data-encoding")]
+pub use data_encoding;
 pub use maybe_string;
 
 // UNIX signals
@@ -253,6 +282,10 @@ pub use uninterruptible;
 #[cfg(feature = "directories")]
 pub use directories;
 
+// Serialization
+#[cfg(feature = "serde")]
+pub use serde;
+
 pub mod prelude {
     // Often used I/O
     pub use std::fs::{
@@ -344,9 +377,11 @@ pub mod prelude {
     pub use mkargs::{mkargs, MkArgs};
     #[cfg(feature = "cradle")]
     pub use cradle::prelude::*;
+    #[cfg(all(target_family = "unix", feature = "libc", feature = "directories"))]
+    pub use super::daemon::*;
 
     // Content hashing and crypto
-    #[cfg(all(feature = "hex", feature = "digest", feature = "sha2"))]
+    #[cfg(all(feature = "hex", feature = "digest", feature = "sha2", feature = "hmac", feature = "subtle", feature = "data-encoding"))]
     pub use super::hashing::*;
 
     #[cfg(feature = "hex")]
@@ -358,9 +393,27 @@ pub mod prelude {
     #[cfg(feature = "directories")]
     pub use super::app_dir::*;
 
+    // Locking and PID files
+    #[cfg(all(target_family = "unix", feature = "libc", feature = "directories"))]
+    pub use super::lock::*;
+
+    // Caching of computation results
+    #[cfg(all(feature = "directories", feature = "tempfile", feature = "hex", feature = "digest", feature = "sha2", feature = "hmac", feature = "subtle", feature = "data-encoding"))]
+    pub use super::cache::*;
+
+    // Persistent application state
+    #[cfg(all(target_family = "unix", feature = "libc", feature = "directories", feature = "tempfile"))]
+    pub use super::state::*;
+
+    // Content-addressed storage
+    #[cfg(all(feature = "directories", feature = "tempfile", feature = "filetime", feature = "hex", feature = "digest", feature = "sha2", feature = "hmac", feature = "subtle", feature = "data-encoding"))]
+    pub use super::blob_store::*;
+
     // Time and duration
     #[cfg(feature = "chrono")]
     pub use super::time::*;
+    #[cfg(feature = "chrono")]
+    pub use super::cron::*;
 
     // Iterators
     pub use itertools::*;
@@ -372,6 +425,8 @@ pub mod prelude {
     pub use uninterruptible::Uninterruptible;
     #[cfg(all(target_family = "unix", feature = "signal-hook"))]
     pub use signal_hook::{consts::signal::*, consts::TERM_SIGNALS, iterator::Signals, flag as signal_flag};
+    #[cfg(all(target_family = "unix", feature = "signal-hook", feature = "libc"))]
+    pub use super::signals::*;
 
     // Handy extensions
     pub use boolinator::Boolinator;
@@ -382,6 +437,7 @@ pub mod prelude {
     pub use ansi_term::{Colour, Style, ANSIString, ANSIStrings, unstyle};
     #[cfg(feature = "zzz")]
     pub use zzz::ProgressBarIterExt;
+    pub use super::progress::*;
     #[cfg(feature = "term_size")]
     pub use term_size::dimensions as term_dimensions;
 
@@ -411,7 +467,7 @@ pub mod prelude {
 
     #[cfg(all(feature = "clap", feature = "log"))]
     impl ArgsDryRun {
-        pub fn run(&self, msg: impl Display, run: impl FnOnce() -> ()) -> () {
+        pub fn run(&self, msg: impl Display, run: impl FnOnce()) {
             if self.enabled {
                 info!("[dry run]: {}", msg);
             } else {
@@ -0,0 +1,223 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::fmt::{self, Display};
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use crate::app_dir::{app_data, app_runtime, AppDirError};
+
+/// How long to wait for a lock held by another process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockWait {
+    /// Fail immediately if lock is held by another process.
+    NoWait,
+    /// Wait up to given duration for the lock to be released.
+    Timeout(Duration),
+    /// Wait until the lock is released.
+    Forever,
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    AppDirError(AppDirError),
+    IoError(PathBuf, io::Error),
+    Locked { path: PathBuf, pid: Option<u32> },
+}
+
+impl From<AppDirError> for LockError {
+    fn from(err: AppDirError) -> LockError {
+        LockError::AppDirError(err)
+    }
+}
+
+impl Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::AppDirError(_) => write!(f, "failed to locate lock file directory"),
+            LockError::IoError(path, _) => write!(f, "I/O error while locking file {:?}", path),
+            LockError::Locked { path, pid: Some(pid) } => write!(f, "lock file {:?} is held by process {}", path, pid),
+            LockError::Locked { path, pid: None } => write!(f, "lock file {:?} is held by another process", path),
+        }
+    }
+}
+
+impl Error for LockError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LockError::AppDirError(err) => Some(err),
+            LockError::IoError(_, err) => Some(err),
+            LockError::Locked { .. } => None,
+        }
+    }
+}
+
+/// Guard holding an advisory exclusive lock (`flock`) on a PID file.
+///
+/// The lock is released when the guard is dropped or the process exits.
+/// The lock file is not removed on release (as this would race with other processes waiting on it) but its content is cleared.
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+    stale_pid: Option<u32>,
+    released: bool,
+}
+
+impl InstanceLock {
+    /// Acquires lock on file at given path and writes PID of current process to it.
+    ///
+    /// Lock files left by processes that died without releasing the lock are taken over; see [InstanceLock::stale_pid].
+    pub fn acquire(path: impl Into<PathBuf>, wait: LockWait) -> Result<InstanceLock, LockError> {
+        let path = path.into();
+        let io_error = |err| LockError::IoError(path.clone(), err);
+
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path).map_err(io_error)?;
+
+        let deadline = match wait {
+            LockWait::Timeout(timeout) => Some(Instant::now() + timeout),
+            _ => None,
+        };
+
+        loop {
+            if wait == LockWait::Forever {
+                flock(&file, libc::LOCK_EX).map_err(io_error)?;
+                break
+            }
+
+            match flock(&file, libc::LOCK_EX | libc::LOCK_NB) {
+                Ok(()) => break,
+                Err(err) if err.kind() != io::ErrorKind::WouldBlock => return Err(io_error(err)),
+                Err(_) => {
+                    if deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(true) {
+                        return Err(LockError::Locked { pid: read_pid(&mut file).ok().flatten(), path })
+                    }
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+            }
+        }
+
+        // We hold the lock so any PID left in the file belongs to a process that did not release it
+        let stale_pid = read_pid(&mut file).map_err(io_error)?.filter(|pid| *pid != std::process::id());
+        #[cfg(feature = "log")]
+        if let Some(pid) = stale_pid {
+            log::warn!("Taking over stale lock file {:?} left by process {}", path, pid);
+        }
+
+        write_pid(&mut file).map_err(io_error)?;
+
+        Ok(InstanceLock { file, path, stale_pid, released: false })
+    }
+
+    /// Rewrites the lock file with PID of current process.
+    ///
+    /// The lock is inherited by child processes so this is useful when the lock is acquired before forking.
+    pub fn update_pid(&mut self) -> Result<(), LockError> {
+        write_pid(&mut self.file).map_err(|err| LockError::IoError(self.path.clone(), err))
+    }
+
+    /// Returns path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns PID of process that previously held the lock and exited without releasing it.
+    pub fn stale_pid(&self) -> Option<u32> {
+        self.stale_pid
+    }
+
+    /// Releases the lock, reporting any errors.
+    pub fn release(mut self) -> Result<(), LockError> {
+        self.released = true;
+        self.clear().map_err(|err| LockError::IoError(self.path.clone(), err))
+    }
+
+    fn clear(&self) -> Result<(), io::Error> {
+        self.file.set_len(0)?;
+        flock(&self.file, libc::LOCK_UN)
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if !self.released {
+            let _ = self.clear();
+        }
+    }
+}
+
+fn flock(file: &File, operation: libc::c_int) -> Result<(), io::Error> {
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(())
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err)
+        }
+    }
+}
+
+fn write_pid(file: &mut File) -> Result<(), io::Error> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    writeln!(file, "{}", std::process::id())?;
+    file.sync_data()
+}
+
+fn read_pid(file: &mut File) -> Result<Option<u32>, io::Error> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_string(&mut content)?;
+    Ok(content.trim().parse().ok())
+}
+
+/// Acquires single-instance lock of the application.
+///
+/// The lock file `instance.lock` is located in application runtime directory, or in data directory if runtime
+/// directory is not available.
+pub fn app_instance_lock(wait: LockWait) -> Result<InstanceLock, LockError> {
+    app_lock("instance", wait)
+}
+
+/// Acquires named application lock.
+///
+/// The lock file `<name>.lock` is located in application runtime directory, or in data directory if runtime
+/// directory is not available.
+pub fn app_lock(name: &str, wait: LockWait) -> Result<InstanceLock, LockError> {
+    let dir = match app_runtime(None) {
+        Ok(dir) => dir,
+        Err(AppDirError::NoRuntimeDir) => app_data(None)?,
+        Err(err) => return Err(err.into()),
+    };
+
+    InstanceLock::acquire(dir.join(format!("{}.lock", name)), wait)
+}
+
+#[cfg(all(test, feature = "tempfile"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.lock");
+
+        let lock = InstanceLock::acquire(&path, LockWait::NoWait).unwrap();
+        assert_eq!(lock.stale_pid(), None);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), format!("{}\n", std::process::id()));
+
+        let pid = std::process::id();
+        assert!(matches!(InstanceLock::acquire(&path, LockWait::NoWait), Err(LockError::Locked { pid: Some(p), .. }) if p == pid));
+        assert!(matches!(InstanceLock::acquire(&path, LockWait::Timeout(Duration::from_millis(100))), Err(LockError::Locked { .. })));
+
+        drop(lock);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+        std::fs::write(&path, "4194305\n").unwrap();
+        let lock = InstanceLock::acquire(&path, LockWait::Forever).unwrap();
+        assert_eq!(lock.stale_pid(), Some(4194305));
+        lock.release().unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+use std::fmt::{self, Display};
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+#[cfg(all(target_family = "unix", feature = "signal-hook"))]
+use std::io::{self, Write};
+
+#[cfg(all(target_family = "unix", feature = "signal-hook"))]
+use signal_hook::iterator::{Handle, Signals};
+
+const UNKNOWN_TOTAL: u64 = u64::MAX;
+
+static PROGRESS_REGISTRY: Mutex<Vec<Weak<ProgressState>>> = Mutex::new(Vec::new());
+
+#[derive(Debug)]
+struct ProgressState {
+    name: String,
+    done: AtomicU64,
+    total: AtomicU64,
+    message: Mutex<Option<String>>,
+    started: Instant,
+}
+
+/// Progress of a task registered in process wide registry so it can be reported on demand (see [dump_progress_on_signal]).
+///
+/// The task is removed from the registry when the last clone of the handle is dropped.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    state: Arc<ProgressState>,
+}
+
+impl Progress {
+    /// Registers new task with given name and total number of items if known.
+    pub fn register(name: impl Into<String>, total: Option<u64>) -> Progress {
+        let state = Arc::new(ProgressState {
+            name: name.into(),
+            done: AtomicU64::new(0),
+            total: AtomicU64::new(total.unwrap_or(UNKNOWN_TOTAL)),
+            message: Mutex::new(None),
+            started: Instant::now(),
+        });
+
+        let mut registry = PROGRESS_REGISTRY.lock().unwrap();
+        registry.retain(|entry| entry.strong_count() > 0);
+        registry.push(Arc::downgrade(&state));
+
+        Progress { state }
+    }
+
+    /// Returns name of the task.
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// Adds given number of done items.
+    pub fn inc(&self, count: u64) {
+        self.state.done.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Sets number of done items.
+    pub fn set(&self, done: u64) {
+        self.state.done.store(done, Ordering::Relaxed);
+    }
+
+    /// Returns number of done items.
+    pub fn done(&self) -> u64 {
+        self.state.done.load(Ordering::Relaxed)
+    }
+
+    /// Sets total number of items.
+    pub fn set_total(&self, total: Option<u64>) {
+        self.state.total.store(total.unwrap_or(UNKNOWN_TOTAL), Ordering::Relaxed);
+    }
+
+    /// Returns total number of items if known.
+    pub fn total(&self) -> Option<u64> {
+        Some(self.state.total.load(Ordering::Relaxed)).filter(|total| *total != UNKNOWN_TOTAL)
+    }
+
+    /// Sets message describing current step of the task.
+    pub fn set_message(&self, message: impl Into<String>) {
+        *self.state.message.lock().unwrap() = Some(message.into());
+    }
+
+    /// Clears message describing current step of the task.
+    pub fn clear_message(&self) {
+        *self.state.message.lock().unwrap() = None;
+    }
+
+    /// Returns time elapsed since the task was registered.
+    pub fn elapsed(&self) -> Duration {
+        self.state.started.elapsed()
+    }
+
+    /// Returns estimated time remaining to complete the task if total is known.
+    pub fn eta(&self) -> Option<Duration> {
+        let done = self.done();
+        let total = self.total()?;
+        if done == 0 || done > total {
+            return None
+        }
+        Some(self.elapsed().mul_f64((total - done) as f64 / done as f64))
+    }
+}
+
+impl Display for Progress {
+    /// Formats status line like `download: 42/100 (42.0%) in 4.20s, ETA 5.80s: file.txt`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let done = self.done();
+        write!(f, "{}: {}", self.name(), done)?;
+        if let Some(total) = self.total() {
+            let percent = if total == 0 { 100.0 } else { done as f64 / total as f64 * 100.0 };
+            write!(f, "/{} ({:.1}%)", total, percent)?;
+        }
+        write!(f, " in {:.2?}", self.elapsed())?;
+        if let Some(eta) = self.eta() {
+            write!(f, ", ETA {:.2?}", eta)?;
+        }
+        if let Some(message) = self.state.message.lock().unwrap().as_ref() {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns all registered tasks in order of registration.
+pub fn progress_tasks() -> Vec<Progress> {
+    PROGRESS_REGISTRY.lock().unwrap().iter()
+        .filter_map(Weak::upgrade)
+        .map(|state| Progress { state })
+        .collect()
+}
+
+/// Returns status lines of all registered tasks.
+pub fn progress_status() -> String {
+    progress_tasks().iter().map(|progress| format!("{}\n", progress)).collect()
+}
+
+/// Iterator updating [Progress] for every item.
+#[derive(Debug)]
+pub struct ProgressIter<I> {
+    inner: I,
+    progress: Progress,
+}
+
+impl<I> ProgressIter<I> {
+    /// Returns progress handle of the iteration.
+    pub fn progress_handle(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Returns inner iterator.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Iterator> Iterator for ProgressIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.inner.next();
+        if item.is_some() {
+            self.progress.inc(1);
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+pub trait ProgressTrackExt: Iterator + Sized {
+    /// Registers [Progress] with given name updated as items are iterated.
+    ///
+    /// Total is taken from exact size hint of the iterator.
+    fn track_progress(self, name: impl Into<String>) -> ProgressIter<Self> {
+        let total = match self.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(upper as u64),
+            _ => None,
+        };
+        ProgressIter {
+            inner: self,
+            progress: Progress::register(name, total),
+        }
+    }
+
+    /// Displays `zzz` progress bar and registers [Progress] with given name updated as items are iterated.
+    ///
+    /// Note that progress bars created directly with `zzz` (e.g. `iter.progress()`) are not registered.
+    #[cfg(feature = "zzz")]
+    fn progress_tracked(self, name: impl Into<String>) -> zzz::ProgressBarIter<ProgressIter<Self>> {
+        self.progress_tracked_with(name, zzz::ProgressBar::smart())
+    }
+
+    /// Like [ProgressTrackExt::progress_tracked] but displays given `zzz` progress bar.
+    #[cfg(feature = "zzz")]
+    fn progress_tracked_with(self, name: impl Into<String>, bar: zzz::ProgressBar) -> zzz::ProgressBarIter<ProgressIter<Self>> {
+        zzz::ProgressBarIterExt::with_progress(self.track_progress(name), bar)
+    }
+}
+
+impl<I: Iterator + Sized> ProgressTrackExt for I {}
+
+/// Spawns thread printing status of all registered tasks to stderr when `SIGUSR1` (or `SIGINFO` where available, e.g. `Ctrl-T` on BSD terminals) is received.
+///
+/// Reporting stops when returned handle is closed.
+#[cfg(all(target_family = "unix", feature = "signal-hook"))]
+pub fn dump_progress_on_signal() -> Result<Handle, io::Error> {
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly", target_os = "netbsd", target_os = "openbsd", target_os = "macos"))]
+    let mut signals = Signals::new([signal_hook::consts::SIGUSR1, signal_hook::consts::SIGINFO])?;
+    #[cfg(not(any(target_os = "freebsd", target_os = "dragonfly", target_os = "netbsd", target_os = "openbsd", target_os = "macos")))]
+    let mut signals = Signals::new([signal_hook::consts::SIGUSR1])?;
+    let handle = signals.handle();
+
+    std::thread::Builder::new().name("progress".to_owned()).spawn(move || {
+        for _ in signals.forever() {
+            let status = progress_status();
+            let stderr = io::stderr();
+            let mut stderr = stderr.lock();
+            let _ = if status.is_empty() { writeln!(stderr, "no tasks in progress") } else { write!(stderr, "{}", status) };
+        }
+    })?;
+
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_registry() {
+        let download = Progress::register("download", Some(4));
+        download.inc(1);
+        download.set_message("file.txt");
+        let status = download.to_string();
+        assert!(status.starts_with("download: 1/4 (25.0%) in "), "{}", status);
+        assert!(status.contains(", ETA "));
+        assert!(status.ends_with(": file.txt"));
+        download.clear_message();
+        assert!(!download.to_string().ends_with(": file.txt"));
+
+        let mut items = vec![1, 2, 3].into_iter().track_progress("items");
+        items.next();
+        assert_eq!(items.progress_handle().done(), 1);
+        assert_eq!(items.progress_handle().total(), Some(3));
+        assert!(progress_tasks().iter().any(|task| task.name() == "items"));
+        assert_eq!(items.by_ref().count(), 2);
+        assert_eq!(items.progress_handle().done(), 3);
+
+        drop(items);
+        assert!(!progress_tasks().iter().any(|task| task.name() == "items"));
+
+        #[cfg(feature = "zzz")]
+        {
+            struct Hidden;
+
+            impl zzz::ProgressBarTheme for Hidden {
+                fn render(&self, _bar: &zzz::ProgressBar) -> Result<(), zzz::RenderError> {
+                    Ok(())
+                }
+            }
+
+            static HIDDEN: zzz::ProgressBarConfig = zzz::ProgressBarConfig { width: None, min_bar_width: 5, theme: &Hidden, max_fps: 60.0 };
+
+            let mut bar = vec![1, 2].into_iter().progress_tracked_with("bar", zzz::ProgressBar::smart().config(&HIDDEN));
+            bar.next();
+            assert!(progress_tasks().iter().any(|task| task.name() == "bar" && task.done() == 1 && task.total() == Some(2)));
+            // zzz prints new line to stderr when bar is dropped
+            std::mem::forget(bar);
+        }
+        assert!(progress_status().contains("download: 1/4"));
+    }
+}
@@ -0,0 +1,373 @@
+use std::io;
+use std::mem;
+use std::ptr;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use signal_hook::consts::{SIGHUP, TERM_SIGNALS};
+use signal_hook::iterator::{Handle, Signals};
+use signal_hook::low_level;
+
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Process wide state of termination signal handling.
+#[derive(Debug)]
+struct TermState {
+    requested: Arc<AtomicBool>,
+    received: AtomicUsize,
+    signal: AtomicUsize,
+    graceful: AtomicBool,
+    critical: AtomicUsize,
+    default_actions: AtomicUsize,
+    deferred_signal: AtomicUsize,
+    deferred_count: AtomicUsize,
+}
+
+static TERM_STATE: OnceLock<TermState> = OnceLock::new();
+static TERM_HANDLER: Mutex<bool> = Mutex::new(false);
+
+fn term_state() -> &'static TermState {
+    TERM_STATE.get_or_init(|| TermState {
+        requested: Arc::new(AtomicBool::new(false)),
+        received: AtomicUsize::new(0),
+        signal: AtomicUsize::new(0),
+        graceful: AtomicBool::new(false),
+        critical: AtomicUsize::new(0),
+        default_actions: AtomicUsize::new(0),
+        deferred_signal: AtomicUsize::new(0),
+        deferred_count: AtomicUsize::new(0),
+    })
+}
+
+// Must be async-signal-safe: only atomics and signal-hook low level functions are used
+fn handle_term_signal(state: &TermState, signal: i32) {
+    if state.critical.load(Ordering::SeqCst) > 0 {
+        state.deferred_signal.store(signal as usize, Ordering::SeqCst);
+        state.deferred_count.fetch_add(1, Ordering::SeqCst);
+        // Last critical section may have ended before the signal was recorded
+        if state.critical.load(Ordering::SeqCst) == 0 {
+            handle_deferred_signals(state);
+        }
+        return
+    }
+
+    if !state.graceful.load(Ordering::SeqCst) {
+        // Leave the signal to other handlers unless nothing else would terminate the program
+        if state.default_actions.load(Ordering::SeqCst) & (1 << signal) != 0 {
+            let _ = low_level::emulate_default_handler(signal);
+        }
+        return
+    }
+
+    // Shutdown requested programmatically does not count as the first signal
+    if state.received.fetch_add(1, Ordering::SeqCst) > 0 {
+        // Second signal: user insists on termination
+        low_level::exit(128 + signal)
+    }
+    state.signal.store(signal as usize, Ordering::SeqCst);
+    state.requested.store(true, Ordering::SeqCst);
+}
+
+// Returns the last deferred signal and number of deferred signals
+fn take_deferred_signals(state: &TermState) -> Option<(i32, usize)> {
+    match state.deferred_count.swap(0, Ordering::SeqCst) {
+        0 => None,
+        count => Some((state.deferred_signal.load(Ordering::SeqCst) as i32, count)),
+    }
+}
+
+fn handle_deferred_signals(state: &TermState) {
+    if let Some((signal, count)) = take_deferred_signals(state) {
+        // Second signal already terminates the program
+        for _ in 0..count.min(2) {
+            handle_term_signal(state, signal);
+        }
+    }
+}
+
+// Returns true if the signal has default disposition, i.e. no handler was installed for it
+fn has_default_action(signal: i32) -> Result<bool, io::Error> {
+    let mut action: libc::sigaction = unsafe { mem::zeroed() };
+    if unsafe { libc::sigaction(signal, ptr::null(), &mut action) } == -1 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(action.sa_sigaction == libc::SIG_DFL)
+}
+
+fn install_term_handler() -> Result<&'static TermState, io::Error> {
+    let state = term_state();
+    let mut installed = TERM_HANDLER.lock().unwrap();
+    if !*installed {
+        for signal in TERM_SIGNALS {
+            let signal = *signal;
+            if has_default_action(signal)? {
+                state.default_actions.fetch_or(1 << signal, Ordering::SeqCst);
+            }
+            // Safety: the handler is async-signal-safe
+            unsafe { low_level::register(signal, move || handle_term_signal(state, signal))?; }
+        }
+        *installed = true;
+    }
+    Ok(state)
+}
+
+/// Token signalling that graceful shutdown of the program was requested by a termination signal (`TERM_SIGNALS`).
+///
+/// After the first termination signal is received [ShutdownToken::is_requested] returns true and the program is expected
+/// to wind down; a second signal terminates the program immediately with exit status `128 + signal`.
+/// Shutdown requested with [ShutdownToken::request] does not count as a received signal.
+///
+/// All tokens share process wide state and can be cheaply cloned.
+/// Use [ShutdownToken::flag] to interrupt waiting on `Interval`, `RateLimiter` or `CronSchedule`.
+#[derive(Debug, Clone)]
+pub struct ShutdownToken {
+    state: &'static TermState,
+}
+
+impl ShutdownToken {
+    /// Registers handlers for termination signals and returns the token.
+    pub fn register() -> Result<ShutdownToken, io::Error> {
+        let state = install_term_handler()?;
+        state.graceful.store(true, Ordering::SeqCst);
+        Ok(ShutdownToken { state })
+    }
+
+    /// Returns true if shutdown was requested.
+    pub fn is_requested(&self) -> bool {
+        self.state.requested.load(Ordering::SeqCst)
+    }
+
+    /// Returns the signal that requested shutdown, if shutdown was requested by a signal.
+    pub fn signal(&self) -> Option<i32> {
+        match self.state.signal.load(Ordering::SeqCst) {
+            0 => None,
+            signal => Some(signal as i32),
+        }
+    }
+
+    /// Requests shutdown as if termination signal was received.
+    pub fn request(&self) {
+        self.state.requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns flag that is set when shutdown is requested.
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.state.requested)
+    }
+
+    /// Blocks until shutdown is requested.
+    pub fn wait(&self) {
+        while !self.is_requested() {
+            std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+        }
+    }
+
+    /// Blocks until shutdown is requested or timeout elapses; returns true if shutdown was requested.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.is_requested() {
+                return true
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false
+            }
+            std::thread::sleep(remaining.min(SHUTDOWN_POLL_INTERVAL));
+        }
+    }
+
+    /// Sleeps for given duration unless shutdown is requested; returns false if sleep was cut short by shutdown request.
+    ///
+    /// This is useful in polling loops: `while token.sleep(interval) { ... }`.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        !self.wait_timeout(duration)
+    }
+}
+
+/// Guard deferring termination signals (`TERM_SIGNALS`) while critical work (e.g. writing a file) is in progress.
+///
+/// Termination signals received while the guard is alive are handled when the last guard is dropped:
+/// if [ShutdownToken] was registered graceful shutdown is requested, otherwise the program is terminated as by default
+/// unless other handler was registered for the signal before the first guard was entered.
+/// Deferred signals are counted so that two or more signals received during critical section terminate the program
+/// immediately as a second signal would; only the last received signal number is kept.
+#[derive(Debug)]
+pub struct CriticalSection {
+    state: &'static TermState,
+}
+
+impl CriticalSection {
+    /// Starts deferring termination signals until returned guard is dropped.
+    pub fn enter() -> Result<CriticalSection, io::Error> {
+        let state = install_term_handler()?;
+        state.critical.fetch_add(1, Ordering::SeqCst);
+        Ok(CriticalSection { state })
+    }
+}
+
+impl Drop for CriticalSection {
+    fn drop(&mut self) {
+        if self.state.critical.fetch_sub(1, Ordering::SeqCst) == 1 {
+            if let Some((signal, count)) = take_deferred_signals(self.state) {
+                #[cfg(feature = "log")]
+                log::warn!("Handling {} {} signal(s) deferred during critical section", count, low_level::signal_name(signal).unwrap_or("termination"));
+                for _ in 0..count.min(2) {
+                    handle_term_signal(self.state, signal);
+                }
+            }
+        }
+    }
+}
+
+/// Runs given function deferring termination signals until it returns; see [CriticalSection].
+pub fn critical_section<T>(f: impl FnOnce() -> T) -> Result<T, io::Error> {
+    let _guard = CriticalSection::enter()?;
+    Ok(f())
+}
+
+/// Shared value (e.g. configuration) that can be atomically replaced while readers hold on to the previous version.
+#[derive(Debug)]
+pub struct Reloadable<T> {
+    current: Arc<RwLock<Arc<T>>>,
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Reloadable<T> {
+        Reloadable { current: Arc::clone(&self.current) }
+    }
+}
+
+impl<T: Send + Sync + 'static> Reloadable<T> {
+    /// Creates reloadable value with given initial value.
+    pub fn new(value: T) -> Reloadable<T> {
+        Reloadable { current: Arc::new(RwLock::new(Arc::new(value))) }
+    }
+
+    /// Returns snapshot of the current value; it is not affected by subsequent reloads.
+    pub fn get(&self) -> Arc<T> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    /// Replaces the current value.
+    pub fn set(&self, value: T) {
+        *self.current.write().unwrap() = Arc::new(value);
+    }
+
+    /// Replaces current value with one returned by the loader; current value is kept if loader fails.
+    pub fn reload<E>(&self, loader: impl FnOnce() -> Result<T, E>) -> Result<(), E> {
+        self.set(loader()?);
+        Ok(())
+    }
+
+    /// Spawns thread that reloads the value with the loader on every `SIGHUP` signal.
+    ///
+    /// Reload failures are logged and the current value is kept.
+    /// Reloading stops when returned handle is closed.
+    pub fn reload_on_hup<E: Display>(&self, mut loader: impl FnMut() -> Result<T, E> + Send + 'static) -> Result<Handle, io::Error> {
+        let mut signals = Signals::new([SIGHUP])?;
+        let handle = signals.handle();
+        let reloadable = self.clone();
+
+        std::thread::Builder::new().name("reload".to_owned()).spawn(move || {
+            for _ in signals.forever() {
+                match reloadable.reload(&mut loader) {
+                    Ok(()) => {
+                        #[cfg(feature = "log")]
+                        log::info!("Reloaded configuration on SIGHUP");
+                    }
+                    Err(_err) => {
+                        #[cfg(feature = "log")]
+                        log::error!("Failed to reload configuration on SIGHUP; keeping current configuration: {}", _err);
+                    }
+                }
+            }
+        })?;
+
+        Ok(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signal_hook::consts::SIGTERM;
+
+    // Signal handling state is process wide so all tests using it are run sequentially from one test
+    #[test]
+    fn term_signals() {
+        // Handler registered by the program before entering critical section is not overridden
+        let flag = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(SIGTERM, Arc::clone(&flag)).unwrap();
+        critical_section(|| ()).unwrap();
+        low_level::raise(SIGTERM).unwrap();
+        assert!(flag.load(Ordering::SeqCst));
+
+        let token = ShutdownToken::register().unwrap();
+        assert!(!token.is_requested());
+
+        // First signal after programmatic request still only requests shutdown
+        token.request();
+        assert_eq!(token.signal(), None);
+        low_level::raise(SIGTERM).unwrap();
+        assert_eq!(token.signal(), Some(SIGTERM));
+        token.state.requested.store(false, Ordering::SeqCst);
+        token.state.received.store(0, Ordering::SeqCst);
+        token.state.signal.store(0, Ordering::SeqCst);
+
+        assert!(!token.wait_timeout(Duration::from_millis(10)));
+        assert!(token.sleep(Duration::from_millis(10)));
+
+        let outer = CriticalSection::enter().unwrap();
+        let written = critical_section(|| {
+            low_level::raise(SIGTERM).unwrap();
+            assert!(!token.is_requested());
+            42
+        }).unwrap();
+        assert_eq!(written, 42);
+        // Signal is still deferred by the outer section
+        assert!(!token.is_requested());
+        drop(outer);
+        assert!(token.is_requested());
+        assert_eq!(token.signal(), Some(SIGTERM));
+        assert!(token.flag().load(Ordering::SeqCst));
+        assert!(!token.sleep(Duration::from_secs(10)));
+        token.wait();
+    }
+
+    #[test]
+    fn reload_on_hup() {
+        let config = Reloadable::new(0);
+        let generation = Arc::new(AtomicUsize::new(0));
+        let loader_generation = Arc::clone(&generation);
+        let handle = config.reload_on_hup(move || match loader_generation.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok(1),
+            _ => Err("invalid configuration"),
+        }).unwrap();
+
+        let snapshot = config.get();
+        let wait_until = |condition: &dyn Fn() -> bool| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !condition() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        low_level::raise(SIGHUP).unwrap();
+        wait_until(&|| *config.get() == 1);
+        assert_eq!(*config.get(), 1);
+        assert_eq!(*snapshot, 0);
+
+        // Failed reload keeps current value
+        low_level::raise(SIGHUP).unwrap();
+        wait_until(&|| generation.load(Ordering::SeqCst) == 2);
+        assert_eq!(generation.load(Ordering::SeqCst), 2);
+        assert_eq!(*config.get(), 1);
+
+        handle.close();
+        assert_eq!(config.reload(|| Ok::<_, ()>(3)), Ok(()));
+        assert_eq!(*config.get(), 3);
+    }
+}
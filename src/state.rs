@@ -0,0 +1,252 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::fmt::{self, Display};
+use std::error::Error;
+use std::str::FromStr;
+
+use linked_hash_map::LinkedHashMap;
+use tempfile::NamedTempFile;
+
+use crate::app_dir::{app_data, AppDirError};
+use crate::lock::{InstanceLock, LockError, LockWait};
+
+const STATE_HEADER: &str = "# cotton state version ";
+
+#[derive(Debug)]
+pub enum StateError {
+    AppDirError(AppDirError),
+    LockError(LockError),
+    IoError(PathBuf, io::Error),
+    ParseError { path: PathBuf, line: usize },
+    ValueError(String, Box<dyn Error + Send + Sync>),
+    InvalidKey(String),
+}
+
+impl From<AppDirError> for StateError {
+    fn from(err: AppDirError) -> StateError {
+        StateError::AppDirError(err)
+    }
+}
+
+impl From<LockError> for StateError {
+    fn from(err: LockError) -> StateError {
+        StateError::LockError(err)
+    }
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::AppDirError(_) => write!(f, "failed to locate state file directory"),
+            StateError::LockError(_) => write!(f, "failed to lock state file"),
+            StateError::IoError(path, _) => write!(f, "I/O error while accessing state file {:?}", path),
+            StateError::ParseError { path, line } => write!(f, "state file {:?} is malformed at line {}", path, line),
+            StateError::ValueError(key, _) => write!(f, "failed to parse value of state key {:?}", key),
+            StateError::InvalidKey(key) => write!(f, "state key {:?} contains '=' or new line character", key),
+        }
+    }
+}
+
+impl Error for StateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StateError::AppDirError(err) => Some(err),
+            StateError::LockError(err) => Some(err),
+            StateError::IoError(_, err) => Some(err),
+            StateError::ParseError { .. } => None,
+            StateError::ValueError(_, err) => Some(err.as_ref()),
+            StateError::InvalidKey(_) => None,
+        }
+    }
+}
+
+/// Persistent key/value application state (e.g. last processed ID or last run time).
+///
+/// Values are stored as strings and converted with [Display] and [FromStr].
+/// The state file is locked for the lifetime of the store so concurrent runs of the program will not lose updates;
+/// it is written atomically on [StateStore::save].
+///
+/// The state file carries a schema version that is upgraded with [StateStore::migrate].
+#[derive(Debug)]
+pub struct StateStore {
+    path: PathBuf,
+    version: u32,
+    values: LinkedHashMap<String, String>,
+    _lock: InstanceLock,
+}
+
+impl StateStore {
+    /// Opens state store file at given path, waiting for the lock as specified.
+    ///
+    /// Store that does not exist yet is empty and has schema version 0.
+    pub fn open(path: impl Into<PathBuf>, wait: LockWait) -> Result<StateStore, StateError> {
+        let path = path.into();
+        let mut lock_path = path.clone().into_os_string();
+        lock_path.push(".lock");
+        let lock = InstanceLock::acquire(lock_path, wait)?;
+
+        let (version, values) = match fs::read_to_string(&path) {
+            Ok(content) => parse_state(&path, &content)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (0, LinkedHashMap::new()),
+            Err(err) => return Err(StateError::IoError(path, err)),
+        };
+
+        Ok(StateStore {
+            path,
+            version,
+            values,
+            _lock: lock,
+        })
+    }
+
+    /// Opens state store with given name located in application data directory.
+    pub fn open_app(name: &str, wait: LockWait) -> Result<StateStore, StateError> {
+        StateStore::open(app_data(None)?.join(format!("{}.state", name)), wait)
+    }
+
+    /// Returns path of the state file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns schema version of the state.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Runs migration function if schema version of the state is lower than given version and sets state version to it.
+    ///
+    /// Migrations can be chained to upgrade state from any previous version:
+    /// `store.migrate(1, |s| ...)?.migrate(2, |s| ...)?`.
+    pub fn migrate(&mut self, version: u32, migration: impl FnOnce(&mut StateStore) -> Result<(), StateError>) -> Result<&mut StateStore, StateError> {
+        if self.version < version {
+            migration(self)?;
+            self.version = version;
+        }
+        Ok(self)
+    }
+
+    /// Gets value of given key converted with [FromStr].
+    pub fn get<T>(&self, key: &str) -> Result<Option<T>, StateError> where T: FromStr, T::Err: Error + Send + Sync + 'static {
+        self.values.get(key)
+            .map(|value| value.parse().map_err(|err| StateError::ValueError(key.to_owned(), Box::new(err))))
+            .transpose()
+    }
+
+    /// Gets raw string value of given key.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Sets value of given key to value formatted with [Display].
+    ///
+    /// Returns [StateError::InvalidKey] if the key contains `=` or new line characters.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Display) -> Result<(), StateError> {
+        let key = key.into();
+        if key.contains(['=', '\n', '\r']) {
+            return Err(StateError::InvalidKey(key))
+        }
+        self.values.insert(key, value.to_string());
+        Ok(())
+    }
+
+    /// Removes given key returning its raw value.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.values.remove(key)
+    }
+
+    /// Iterates over keys of the state.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    /// Atomically writes state to the state file.
+    pub fn save(&self) -> Result<(), StateError> {
+        let io_error = |err| StateError::IoError(self.path.clone(), err);
+        let dir = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+
+        let mut temp = NamedTempFile::new_in(dir).map_err(io_error)?;
+        writeln!(temp, "{}{}", STATE_HEADER, self.version).map_err(io_error)?;
+        for (key, value) in &self.values {
+            writeln!(temp, "{}={}", key, escape(value)).map_err(io_error)?;
+        }
+        temp.as_file().sync_data().map_err(io_error)?;
+        temp.persist(&self.path).map_err(|err| io_error(err.error))?;
+
+        Ok(())
+    }
+}
+
+fn parse_state(path: &Path, content: &str) -> Result<(u32, LinkedHashMap<String, String>), StateError> {
+    let mut lines = content.lines().enumerate();
+    let parse_error = |line: usize| StateError::ParseError { path: path.to_owned(), line: line + 1 };
+
+    let version = match lines.next() {
+        Some((no, header)) => header.strip_prefix(STATE_HEADER).and_then(|version| version.parse().ok()).ok_or_else(|| parse_error(no))?,
+        None => 0,
+    };
+
+    let mut values = LinkedHashMap::new();
+    for (no, line) in lines {
+        let (key, value) = line.split_once('=').ok_or_else(|| parse_error(no))?;
+        values.insert(key.to_owned(), unescape(value).ok_or_else(|| parse_error(no))?);
+    }
+
+    Ok((version, values))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> Option<String> {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            out.push(match chars.next()? {
+                '\\' => '\\',
+                'n' => '\n',
+                'r' => '\r',
+                _ => return None,
+            });
+        } else {
+            out.push(c);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.state");
+
+        let mut state = StateStore::open(&path, LockWait::NoWait).unwrap();
+        assert_eq!(state.version(), 0);
+        assert!(matches!(StateStore::open(&path, LockWait::NoWait), Err(StateError::LockError(_))));
+
+        state.migrate(1, |state| state.set("last_id", 0)).unwrap();
+        state.set("last_id", 42u64).unwrap();
+        state.set("note", "multi\nline \\ value").unwrap();
+        assert!(matches!(state.set("a=b", 1), Err(StateError::InvalidKey(_))));
+        state.save().unwrap();
+        drop(state);
+
+        let mut state = StateStore::open(&path, LockWait::NoWait).unwrap();
+        assert_eq!(state.version(), 1);
+        assert_eq!(state.get::<u64>("last_id").unwrap(), Some(42));
+        assert_eq!(state.get_str("note"), Some("multi\nline \\ value"));
+        assert!(matches!(state.get::<u64>("note"), Err(StateError::ValueError(..))));
+
+        state.migrate(1, |_| panic!("already migrated")).unwrap()
+            .migrate(2, |state| { state.remove("note"); Ok(()) }).unwrap();
+        assert_eq!(state.version(), 2);
+        assert_eq!(state.keys().collect::<Vec<_>>(), ["last_id"]);
+    }
+}
@@ -1,5 +1,153 @@
 pub use std::num::ParseFloatError;
-pub use std::time::Duration;
+pub use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::fmt::{self, Display};
+use std::error::Error;
+use std::convert::TryFrom;
+use std::fs::Metadata;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "filetime")]
+use filetime::FileTime;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDurationError {
+    Empty,
+    InvalidNumber(String),
+    MissingUnit(String),
+    UnknownUnit(String),
+    Overflow,
+}
+
+impl Display for ParseDurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDurationError::Empty => write!(f, "duration is empty"),
+            ParseDurationError::InvalidNumber(number) => write!(f, "invalid number {:?} in duration", number),
+            ParseDurationError::MissingUnit(number) => write!(f, "missing unit after {:?} in duration; expected one of: ns, us, ms, s, m, h, d, w", number),
+            ParseDurationError::UnknownUnit(unit) => write!(f, "unknown duration unit {:?}; expected one of: ns, us, ms, s, m, h, d, w", unit),
+            ParseDurationError::Overflow => write!(f, "duration is too large"),
+        }
+    }
+}
+
+impl Error for ParseDurationError {
+}
+
+fn unit_nanos(unit: &str) -> Option<u128> {
+    Some(match unit {
+        "ns" => 1,
+        "us" | "µs" => 1_000,
+        "ms" => 1_000_000,
+        "s" => NANOS_PER_SEC,
+        "m" | "min" => 60 * NANOS_PER_SEC,
+        "h" => 60 * 60 * NANOS_PER_SEC,
+        "d" => 24 * 60 * 60 * NANOS_PER_SEC,
+        "w" => 7 * 24 * 60 * 60 * NANOS_PER_SEC,
+        _ => return None,
+    })
+}
+
+/// Parses compound human-friendly duration like `1h30m`, `1.5s` or `2d 4h`.
+///
+/// Supported units are: `ns`, `us` (or `µs`), `ms`, `s`, `m` (or `min`), `h`, `d` and `w`.
+///
+/// This is useful with `clap` to get `Duration`: `#[arg(value_parser = parse_duration)]`
+pub fn parse_duration(val: &str) -> Result<Duration, ParseDurationError> {
+    let mut rest = val.trim_start();
+    if rest.is_empty() {
+        return Err(ParseDurationError::Empty)
+    }
+
+    let mut total: u128 = 0;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        let unit_len = tail.find(|c: char| !c.is_alphabetic()).unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+
+        if number.is_empty() {
+            return Err(ParseDurationError::InvalidNumber(rest.split_whitespace().next().unwrap_or(rest).to_owned()))
+        }
+        if unit.is_empty() {
+            return Err(ParseDurationError::MissingUnit(number.to_owned()))
+        }
+        let unit_nanos = unit_nanos(unit).ok_or_else(|| ParseDurationError::UnknownUnit(unit.to_owned()))?;
+
+        let invalid_number = || ParseDurationError::InvalidNumber(number.to_owned());
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid_number())
+        }
+        let whole: u128 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid_number())? };
+        // Digits beyond nanosecond precision of the largest unit are irrelevant
+        let fraction = &fraction[..fraction.len().min(18)];
+        let fraction_nanos = if fraction.is_empty() {
+            0
+        } else {
+            let numerator: u128 = fraction.parse().map_err(|_| invalid_number())?;
+            numerator * unit_nanos / 10u128.pow(fraction.len() as u32)
+        };
+
+        total = whole.checked_mul(unit_nanos)
+            .and_then(|nanos| nanos.checked_add(fraction_nanos))
+            .and_then(|nanos| nanos.checked_add(total))
+            .ok_or(ParseDurationError::Overflow)?;
+
+        rest = tail.trim_start();
+    }
+
+    let secs = u64::try_from(total / NANOS_PER_SEC).map_err(|_| ParseDurationError::Overflow)?;
+    Ok(Duration::new(secs, (total % NANOS_PER_SEC) as u32))
+}
+
+/// Parses compound human-friendly duration like `1h30m` or `-250ms` as `chrono::Duration`.
+///
+/// See [parse_duration] for supported format; additionally duration can be prefixed with `-` to make it negative.
+///
+/// This is useful with `clap` to get `chrono::Duration`: `#[arg(value_parser = parse_chrono_duration)]`
+pub fn parse_chrono_duration(val: &str) -> Result<chrono::Duration, ParseDurationError> {
+    let val = val.trim();
+    let (negative, val) = match val.strip_prefix('-') {
+        Some(val) => (true, val),
+        None => (false, val),
+    };
+
+    let duration = chrono::Duration::from_std(parse_duration(val)?).map_err(|_| ParseDurationError::Overflow)?;
+    Ok(if negative { -duration } else { duration })
+}
+
+/// Formats duration in compact human-friendly form like `1h30m` or `1s250ms` that can be parsed with [parse_duration].
+pub fn format_duration(duration: Duration) -> String {
+    let mut nanos = duration.as_nanos();
+    if nanos == 0 {
+        return "0s".to_owned()
+    }
+
+    let mut out = String::new();
+    for unit in ["d", "h", "m", "s", "ms", "us", "ns"] {
+        let unit_nanos = unit_nanos(unit).unwrap();
+        let count = nanos / unit_nanos;
+        if count > 0 {
+            out.push_str(&count.to_string());
+            out.push_str(unit);
+            nanos %= unit_nanos;
+        }
+    }
+    out
+}
+
+/// Formats `chrono::Duration` in compact human-friendly form like `1h30m` or `-250ms`.
+pub fn format_chrono_duration(duration: chrono::Duration) -> String {
+    let formatted = format_duration(duration.abs().to_std().expect("absolute duration is not negative"));
+    if duration < chrono::Duration::zero() {
+        format!("-{}", formatted)
+    } else {
+        formatted
+    }
+}
 
 pub trait DurationExt {
     /// Constructs Duration from &str parsed as f64 representing seconds.
@@ -11,6 +159,14 @@ pub trait DurationExt {
     ///
     /// This is useful with `structopt` to get `Duration`: `parse(try_from_str = Duration::from_millis_str)`
     fn from_millis_str(val: &str) -> Result<Duration, ParseFloatError>;
+
+    /// Constructs Duration from &str in human-friendly form (e.g. `1h30m`); see [parse_duration].
+    ///
+    /// This is useful with `clap` to get `Duration`: `#[arg(value_parser = Duration::from_human_str)]`
+    fn from_human_str(val: &str) -> Result<Duration, ParseDurationError>;
+
+    /// Formats Duration in compact human-friendly form (e.g. `1h30m`); see [format_duration].
+    fn to_human_string(&self) -> String;
 }
 
 impl DurationExt for Duration {
@@ -21,6 +177,14 @@ impl DurationExt for Duration {
     fn from_millis_str(val: &str) -> Result<Duration, ParseFloatError> {
         Ok(Duration::from_secs_f64(val.parse::<f64>()? / 1000.0))
     }
+
+    fn from_human_str(val: &str) -> Result<Duration, ParseDurationError> {
+        parse_duration(val)
+    }
+
+    fn to_human_string(&self) -> String {
+        format_duration(*self)
+    }
 }
 
 pub use chrono::prelude::*;
@@ -35,6 +199,338 @@ pub fn today() -> String {
     Local::now().date_naive().format("%Y%m%d").to_string()
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTimestampError {
+    Empty,
+    UnknownFormat(String),
+    InvalidLocalTime(String),
+}
+
+impl Display for ParseTimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseTimestampError::Empty => write!(f, "timestamp is empty"),
+            ParseTimestampError::UnknownFormat(val) => write!(f, "unrecognized timestamp format {:?}; expected RFC 3339, ISO 8601 date, YYYYMMDD or Unix epoch", val),
+            ParseTimestampError::InvalidLocalTime(val) => write!(f, "timestamp {:?} does not exist in the time zone", val),
+        }
+    }
+}
+
+impl Error for ParseTimestampError {}
+
+const TIMESTAMP_OFFSET_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+    "%Y-%m-%dT%H:%M%z",
+    "%d/%b/%Y:%H:%M:%S %z",
+];
+
+// Decimal comma in seconds (e.g. `12:30:00,123` used by log4j) is replaced with dot before parsing
+const TIMESTAMP_NAIVE_FORMATS: [&str; 5] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y%m%dT%H%M%S",
+];
+
+const TIMESTAMP_DATE_FORMATS: [&str; 3] = [
+    "%Y-%m-%d",
+    "%G-W%V-%u",
+    "%Y%m%d",
+];
+
+fn parse_naive_timestamp(val: &str, year: i32) -> Option<NaiveDateTime> {
+    TIMESTAMP_NAIVE_FORMATS.iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(val, format).ok())
+        .or_else(|| TIMESTAMP_DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(val, format).ok()).and_then(|date| date.and_hms_opt(0, 0, 0)))
+        // Ordinal date YYYY-DDD is ambiguous with YYYY-MM when day has no leading zeros
+        .or_else(|| NaiveDate::parse_from_str(val, "%Y-%j").ok().filter(|_| val.len() == 8).and_then(|date| date.and_hms_opt(0, 0, 0)))
+        // Partial ISO 8601 dates: YYYY-MM and YYYY
+        .or_else(|| NaiveDate::parse_from_str(&format!("{}-01", val), "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))
+        .or_else(|| NaiveDate::parse_from_str(&format!("{}-01-01", val), "%Y-%m-%d").ok().filter(|_| val.len() == 4).and_then(|date| date.and_hms_opt(0, 0, 0)))
+        // Syslog format which has no year
+        .or_else(|| NaiveDateTime::parse_from_str(&format!("{} {}", year, val), "%Y %b %e %H:%M:%S").ok())
+}
+
+/// Parses timestamp in one of the common formats, interpreting timestamps without time zone offset in given time zone.
+///
+/// Supported formats are:
+/// * RFC 3339 and ISO 8601 date and time with offset (e.g. `2020-10-09T12:30:00+02:00`, `2020-10-09 12:30:00.123Z`),
+/// * ISO 8601 date and time without offset (e.g. `2020-10-09T12:30:00`, `2020-10-09 12:30`, `20201009T123000`),
+///   also with decimal comma (e.g. `2020-10-09 12:30:00,123`),
+/// * ISO 8601 dates including partial, week and ordinal dates (e.g. `2020-10-09`, `2020-10`, `2020`, `2020-W41-5`, `2020-283`),
+/// * `YYYYMMDD` as produced by [today] (e.g. `20201009`),
+/// * Unix epoch seconds or milliseconds (13 or more digits), optionally prefixed with `@` (e.g. `1602246600`, `@1602246600123`);
+///   values of 4 or 8 digits are years and `YYYYMMDD` dates unless prefixed with `@`,
+/// * RFC 2822 (e.g. `Fri, 09 Oct 2020 12:30:00 +0000`),
+/// * Common Log Format (e.g. `09/Oct/2020:12:30:00 +0000`) and syslog format (e.g. `Oct  9 12:30:00`; current year is assumed).
+///
+/// Dates without time are at midnight. Local times that are ambiguous (e.g. during DST transition) resolve to the earliest time.
+pub fn parse_timestamp_in<Tz: TimeZone>(val: &str, tz: &Tz) -> Result<DateTime<Utc>, ParseTimestampError> {
+    let val = val.trim();
+    if val.is_empty() {
+        return Err(ParseTimestampError::Empty)
+    }
+    let unknown = || ParseTimestampError::UnknownFormat(val.to_owned());
+
+    let (epoch, forced_epoch) = match val.strip_prefix('@') {
+        Some(epoch) => (epoch, true),
+        None => (val, false),
+    };
+    if forced_epoch || (epoch.len() != 8 && epoch.len() != 4 && epoch.bytes().all(|b| b.is_ascii_digit())) {
+        if epoch.is_empty() || !epoch.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(unknown())
+        }
+        let value = epoch.parse::<i64>().map_err(|_| unknown())?;
+        let time = if epoch.len() >= 13 { Utc.timestamp_millis_opt(value) } else { Utc.timestamp_opt(value, 0) };
+        return time.single().ok_or_else(unknown)
+    }
+
+    let decimal = val.replacen(',', ".", 1);
+    if let Some(time) = DateTime::parse_from_rfc3339(val).ok()
+        .or_else(|| DateTime::parse_from_rfc2822(val).ok())
+        .or_else(|| TIMESTAMP_OFFSET_FORMATS.iter().find_map(|format| DateTime::parse_from_str(&decimal, format).ok())) {
+        return Ok(time.with_timezone(&Utc))
+    }
+
+    if let Some(naive) = decimal.strip_suffix(['Z', 'z']) {
+        let naive = parse_naive_timestamp(naive, Utc::now().year()).ok_or_else(unknown)?;
+        return Ok(Utc.from_utc_datetime(&naive))
+    }
+
+    let naive = parse_naive_timestamp(&decimal, Utc::now().with_timezone(tz).year()).ok_or_else(unknown)?;
+    tz.from_local_datetime(&naive).earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| ParseTimestampError::InvalidLocalTime(val.to_owned()))
+}
+
+/// Parses timestamp in one of the common formats interpreting timestamps without offset in local time zone;
+/// see [parse_timestamp_in].
+///
+/// This is useful with `clap`: `#[arg(value_parser = parse_timestamp)]`
+pub fn parse_timestamp(val: &str) -> Result<DateTime<Utc>, ParseTimestampError> {
+    parse_timestamp_in(val, &Local)
+}
+
+/// Parses timestamp in one of the common formats interpreting timestamps without offset in UTC;
+/// see [parse_timestamp_in].
+///
+/// This is useful with `clap`: `#[arg(value_parser = parse_timestamp_utc)]`
+pub fn parse_timestamp_utc(val: &str) -> Result<DateTime<Utc>, ParseTimestampError> {
+    parse_timestamp_in(val, &Utc)
+}
+
+/// Parses calendar date in ISO 8601 (e.g. `2020-10-09`, `2020-W41-5`), `YYYYMMDD` or relative (e.g. `yesterday`, `3 days ago`) format.
+///
+/// Relative dates are based on the local time zone.
+///
+/// This is useful with `clap`: `#[arg(value_parser = parse_date)]`
+pub fn parse_date(val: &str) -> Result<NaiveDate, ParseTimestampError> {
+    let val = val.trim();
+    if val.is_empty() {
+        return Err(ParseTimestampError::Empty)
+    }
+
+    TIMESTAMP_DATE_FORMATS.iter()
+        .find_map(|format| NaiveDate::parse_from_str(val, format).ok())
+        .or_else(|| parse_relative_time(val).ok().map(|time| time.date_naive()))
+        .ok_or_else(|| ParseTimestampError::UnknownFormat(val.to_owned()))
+}
+
+pub trait NaiveDateExt {
+    /// Returns Monday of the ISO week of the date.
+    fn start_of_iso_week(&self) -> NaiveDate;
+
+    /// Returns Sunday of the ISO week of the date.
+    fn end_of_iso_week(&self) -> NaiveDate;
+
+    /// Returns first day of the month of the date.
+    fn start_of_month(&self) -> NaiveDate;
+
+    /// Returns last day of the month of the date.
+    fn end_of_month(&self) -> NaiveDate;
+
+    /// Returns number of days in the month of the date.
+    fn days_in_month(&self) -> u32;
+}
+
+impl NaiveDateExt for NaiveDate {
+    fn start_of_iso_week(&self) -> NaiveDate {
+        self.week(Weekday::Mon).first_day()
+    }
+
+    fn end_of_iso_week(&self) -> NaiveDate {
+        self.week(Weekday::Mon).last_day()
+    }
+
+    fn start_of_month(&self) -> NaiveDate {
+        self.with_day(1).unwrap()
+    }
+
+    fn end_of_month(&self) -> NaiveDate {
+        self.with_day(self.days_in_month()).unwrap()
+    }
+
+    fn days_in_month(&self) -> u32 {
+        (28..=31).rev().find(|day| self.with_day(*day).is_some()).unwrap()
+    }
+}
+
+/// Step of [DateRange] iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStep {
+    Days(u32),
+    Weeks(u32),
+    /// Dates past the end of shorter months are clamped to their last day (e.g. January 31st is followed by February 29th and March 31st).
+    Months(u32),
+    Years(u32),
+}
+
+impl DateStep {
+    fn nth(&self, start: NaiveDate, index: u32) -> Option<NaiveDate> {
+        match *self {
+            DateStep::Days(days) => start.checked_add_days(chrono::Days::new(days as u64 * index as u64)),
+            DateStep::Weeks(weeks) => start.checked_add_days(chrono::Days::new(weeks as u64 * 7 * index as u64)),
+            DateStep::Months(months) => start.checked_add_months(chrono::Months::new(months.checked_mul(index)?)),
+            DateStep::Years(years) => start.checked_add_months(chrono::Months::new(years.checked_mul(12)?.checked_mul(index)?)),
+        }
+    }
+}
+
+/// Range of calendar dates iterated with given step (one day by default).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateRange {
+    start: NaiveDate,
+    end: NaiveDate,
+    inclusive: bool,
+    step: DateStep,
+    index: u32,
+}
+
+impl DateRange {
+    /// Creates range of dates from start up to but not including end.
+    pub fn new(start: NaiveDate, end: NaiveDate) -> DateRange {
+        DateRange {
+            start,
+            end,
+            inclusive: false,
+            step: DateStep::Days(1),
+            index: 0,
+        }
+    }
+
+    /// Creates range of dates from start up to and including end.
+    pub fn inclusive(start: NaiveDate, end: NaiveDate) -> DateRange {
+        DateRange {
+            inclusive: true,
+            ..DateRange::new(start, end)
+        }
+    }
+
+    /// Creates inclusive range of days of given ISO week.
+    pub fn iso_week(year: i32, week: u32) -> Option<DateRange> {
+        let start = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)?;
+        Some(DateRange::inclusive(start, start.end_of_iso_week()))
+    }
+
+    /// Creates inclusive range of days of given month.
+    pub fn month(year: i32, month: u32) -> Option<DateRange> {
+        let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+        Some(DateRange::inclusive(start, start.end_of_month()))
+    }
+
+    /// Sets iteration step.
+    pub fn with_step(mut self, step: DateStep) -> DateRange {
+        assert!(!matches!(step, DateStep::Days(0) | DateStep::Weeks(0) | DateStep::Months(0) | DateStep::Years(0)), "date range step must not be zero");
+        self.step = step;
+        self
+    }
+
+    /// Returns first date of the range.
+    pub fn first_date(&self) -> NaiveDate {
+        self.start
+    }
+
+    /// Returns last date of the range (inclusive); None if the range is empty.
+    pub fn last_date(&self) -> Option<NaiveDate> {
+        if self.inclusive { Some(self.end) } else { self.end.pred_opt() }.filter(|last| *last >= self.start)
+    }
+
+    /// Returns true if given date is within the range.
+    pub fn contains(&self, date: &NaiveDate) -> bool {
+        *date >= self.start && if self.inclusive { *date <= self.end } else { *date < self.end }
+    }
+
+    fn periods(&self, period_end: fn(&NaiveDate) -> NaiveDate) -> impl Iterator<Item = DateRange> {
+        let last = self.last_date();
+        let first = last.map(|_| self.start);
+        std::iter::successors(first, move |start| period_end(start).succ_opt().filter(|next| Some(*next) <= last))
+            .map(move |start| DateRange::inclusive(start, period_end(&start).min(last.unwrap())))
+    }
+
+    /// Splits the range into ISO weeks (Monday to Sunday); first and last week are clipped to the range.
+    pub fn iso_weeks(&self) -> impl Iterator<Item = DateRange> {
+        self.periods(NaiveDate::end_of_iso_week)
+    }
+
+    /// Splits the range into calendar months; first and last month are clipped to the range.
+    pub fn months(&self) -> impl Iterator<Item = DateRange> {
+        self.periods(NaiveDate::end_of_month)
+    }
+}
+
+impl Iterator for DateRange {
+    type Item = NaiveDate;
+
+    fn next(&mut self) -> Option<NaiveDate> {
+        let date = self.step.nth(self.start, self.index).filter(|date| self.contains(date))?;
+        self.index += 1;
+        Some(date)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateRangeError {
+    Reversed { from: NaiveDate, to: NaiveDate },
+}
+
+impl Display for DateRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateRangeError::Reversed { from, to } => write!(f, "start date {} is after end date {}", from, to),
+        }
+    }
+}
+
+impl Error for DateRangeError {}
+
+/// Command line arguments selecting inclusive range of dates.
+#[cfg(feature = "clap")]
+#[derive(Debug, clap::Args)]
+pub struct ArgsDateRange {
+    /// First date of the range (e.g. 2020-10-01, 20201001, yesterday); defaults to end date
+    #[arg(long, value_parser = parse_date)]
+    pub from: Option<NaiveDate>,
+
+    /// Last date of the range, inclusive (e.g. 2020-10-31, 20201031, today); defaults to today
+    #[arg(long, value_parser = parse_date)]
+    pub to: Option<NaiveDate>,
+}
+
+#[cfg(feature = "clap")]
+impl ArgsDateRange {
+    /// Returns inclusive range of dates selected by the arguments, checking that start date is not after end date.
+    pub fn range(&self) -> Result<DateRange, DateRangeError> {
+        let to = self.to.unwrap_or_else(|| Local::now().date_naive());
+        let from = self.from.unwrap_or(to);
+        if from > to {
+            return Err(DateRangeError::Reversed { from, to })
+        }
+        Ok(DateRange::inclusive(from, to))
+    }
+}
+
 pub trait ChoronoDurationExt {
     /// Constructs Duration from &str parsed as f64 representing seconds.
     ///
@@ -45,6 +541,14 @@ pub trait ChoronoDurationExt {
     ///
     /// This is useful with `structopt` to get `chrono::Duration`: `parse(try_from_str = chrono::Duration::from_millis_str)`
     fn from_millis_str(val: &str) -> Result<chrono::Duration, ParseFloatError>;
+
+    /// Constructs Duration from &str in human-friendly form (e.g. `1h30m` or `-5m`); see [parse_chrono_duration].
+    ///
+    /// This is useful with `clap` to get `chrono::Duration`: `#[arg(value_parser = chrono::Duration::from_human_str)]`
+    fn from_human_str(val: &str) -> Result<chrono::Duration, ParseDurationError>;
+
+    /// Formats Duration in compact human-friendly form (e.g. `1h30m`); see [format_chrono_duration].
+    fn to_human_string(&self) -> String;
 }
 
 impl ChoronoDurationExt for chrono::Duration {
@@ -55,6 +559,418 @@ impl ChoronoDurationExt for chrono::Duration {
     fn from_millis_str(val: &str) -> Result<chrono::Duration, ParseFloatError> {
         Ok(chrono::Duration::from_std(Duration::from_millis_str(val)?).unwrap())
     }
+
+    fn from_human_str(val: &str) -> Result<chrono::Duration, ParseDurationError> {
+        parse_chrono_duration(val)
+    }
+
+    fn to_human_string(&self) -> String {
+        format_chrono_duration(*self)
+    }
+}
+
+pub trait SystemTimeExt {
+    /// Converts to `DateTime` in UTC time zone.
+    fn to_utc(&self) -> DateTime<Utc>;
+
+    /// Converts to `DateTime` in local time zone.
+    fn to_local(&self) -> DateTime<Local>;
+
+    /// Converts to `FileTime`.
+    #[cfg(feature = "filetime")]
+    fn to_file_time(&self) -> FileTime;
+}
+
+impl SystemTimeExt for SystemTime {
+    fn to_utc(&self) -> DateTime<Utc> {
+        DateTime::from(*self)
+    }
+
+    fn to_local(&self) -> DateTime<Local> {
+        DateTime::from(*self)
+    }
+
+    #[cfg(feature = "filetime")]
+    fn to_file_time(&self) -> FileTime {
+        FileTime::from_system_time(*self)
+    }
+}
+
+#[cfg(feature = "filetime")]
+pub trait FileTimeExt {
+    /// Converts to `SystemTime`.
+    fn to_system_time(&self) -> SystemTime;
+
+    /// Converts to `DateTime` in UTC time zone.
+    fn to_utc(&self) -> DateTime<Utc>;
+
+    /// Converts to `DateTime` in local time zone.
+    fn to_local(&self) -> DateTime<Local>;
+}
+
+#[cfg(feature = "filetime")]
+impl FileTimeExt for FileTime {
+    fn to_system_time(&self) -> SystemTime {
+        // Nanoseconds are always positive offset from the (possibly negative) seconds
+        let seconds = self.unix_seconds();
+        let nanos = Duration::from_nanos(self.nanoseconds() as u64);
+        if seconds >= 0 {
+            UNIX_EPOCH + Duration::from_secs(seconds as u64) + nanos
+        } else {
+            UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()) + nanos
+        }
+    }
+
+    fn to_utc(&self) -> DateTime<Utc> {
+        self.to_system_time().to_utc()
+    }
+
+    fn to_local(&self) -> DateTime<Local> {
+        self.to_system_time().to_local()
+    }
+}
+
+pub trait DateTimeExt {
+    /// Converts to `SystemTime`.
+    fn to_system_time(&self) -> SystemTime;
+
+    /// Converts to `FileTime`.
+    #[cfg(feature = "filetime")]
+    fn to_file_time(&self) -> FileTime;
+}
+
+impl<Tz: TimeZone> DateTimeExt for DateTime<Tz> {
+    fn to_system_time(&self) -> SystemTime {
+        SystemTime::from(self.clone())
+    }
+
+    #[cfg(feature = "filetime")]
+    fn to_file_time(&self) -> FileTime {
+        FileTime::from_system_time(self.to_system_time())
+    }
+}
+
+/// File metadata timestamps as `DateTime`.
+pub trait MetadataTimeExt {
+    /// Returns last modification time in UTC time zone.
+    fn modified_utc(&self) -> Result<DateTime<Utc>, io::Error>;
+
+    /// Returns last modification time in local time zone.
+    fn modified_local(&self) -> Result<DateTime<Local>, io::Error>;
+
+    /// Returns last access time in UTC time zone.
+    fn accessed_utc(&self) -> Result<DateTime<Utc>, io::Error>;
+
+    /// Returns last access time in local time zone.
+    fn accessed_local(&self) -> Result<DateTime<Local>, io::Error>;
+
+    /// Returns creation time in UTC time zone; not available on all platforms and file systems.
+    fn created_utc(&self) -> Result<DateTime<Utc>, io::Error>;
+
+    /// Returns creation time in local time zone; not available on all platforms and file systems.
+    fn created_local(&self) -> Result<DateTime<Local>, io::Error>;
+}
+
+impl MetadataTimeExt for Metadata {
+    fn modified_utc(&self) -> Result<DateTime<Utc>, io::Error> {
+        Ok(self.modified()?.to_utc())
+    }
+
+    fn modified_local(&self) -> Result<DateTime<Local>, io::Error> {
+        Ok(self.modified()?.to_local())
+    }
+
+    fn accessed_utc(&self) -> Result<DateTime<Utc>, io::Error> {
+        Ok(self.accessed()?.to_utc())
+    }
+
+    fn accessed_local(&self) -> Result<DateTime<Local>, io::Error> {
+        Ok(self.accessed()?.to_local())
+    }
+
+    fn created_utc(&self) -> Result<DateTime<Utc>, io::Error> {
+        Ok(self.created()?.to_utc())
+    }
+
+    fn created_local(&self) -> Result<DateTime<Local>, io::Error> {
+        Ok(self.created()?.to_local())
+    }
+}
+
+const RELATIVE_UNITS: [(&str, i64); 7] = [
+    ("year", 365 * 24 * 3600),
+    ("month", 30 * 24 * 3600),
+    ("week", 7 * 24 * 3600),
+    ("day", 24 * 3600),
+    ("hour", 3600),
+    ("minute", 60),
+    ("second", 1),
+];
+
+/// Formats time relative to given reference time like `3 minutes ago` or `in 2 days`.
+///
+/// Precision is the number of units, starting from the largest non-zero one, included in the output
+/// (e.g. with precision of 2: `1 hour 5 minutes ago`). Times within a second of reference time are formatted as `just now`.
+/// Months and years are approximated as 30 and 365 days.
+pub fn format_relative_to<Tz: TimeZone, Tz2: TimeZone>(time: &DateTime<Tz>, now: &DateTime<Tz2>, precision: usize) -> String {
+    let diff = time.clone().signed_duration_since(now.clone());
+    let mut seconds = diff.num_seconds().abs();
+
+    let mut parts = Vec::new();
+    let mut first_unit = None;
+    for (index, (unit, unit_seconds)) in RELATIVE_UNITS.iter().enumerate() {
+        let count = seconds / unit_seconds;
+        seconds %= unit_seconds;
+        if count > 0 {
+            first_unit.get_or_insert(index);
+            parts.push(format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" }));
+        }
+        if first_unit.map(|first| index + 1 >= first + precision.max(1)).unwrap_or(false) {
+            break
+        }
+    }
+
+    if parts.is_empty() {
+        "just now".to_owned()
+    } else if diff < chrono::Duration::zero() {
+        format!("{} ago", parts.join(" "))
+    } else {
+        format!("in {}", parts.join(" "))
+    }
+}
+
+/// Formats time relative to current time like `3 minutes ago` or `in 2 days`; see [format_relative_to].
+pub fn format_relative<Tz: TimeZone>(time: &DateTime<Tz>, precision: usize) -> String {
+    format_relative_to(time, &Utc::now(), precision)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRelativeTimeError {
+    Empty,
+    UnknownExpression(String),
+    InvalidDuration(ParseDurationError),
+    OutOfRange,
+}
+
+impl Display for ParseRelativeTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRelativeTimeError::Empty => write!(f, "relative time expression is empty"),
+            ParseRelativeTimeError::UnknownExpression(expr) => write!(f, "unknown relative time expression {:?}", expr),
+            ParseRelativeTimeError::InvalidDuration(_) => write!(f, "invalid duration in relative time expression"),
+            ParseRelativeTimeError::OutOfRange => write!(f, "relative time is out of range"),
+        }
+    }
+}
+
+impl Error for ParseRelativeTimeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseRelativeTimeError::InvalidDuration(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+fn start_of_day<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> Result<DateTime<Tz>, ParseRelativeTimeError> {
+    tz.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap()).earliest().ok_or(ParseRelativeTimeError::OutOfRange)
+}
+
+fn shift_by_units<Tz: TimeZone>(now: &DateTime<Tz>, count: i64, unit: &str) -> Option<DateTime<Tz>> {
+    let months = |count: i64| u32::try_from(count.unsigned_abs()).ok().map(chrono::Months::new);
+    match unit {
+        "month" | "year" => {
+            let months = months(if unit == "year" { count.checked_mul(12)? } else { count })?;
+            if count < 0 { now.clone().checked_sub_months(months) } else { now.clone().checked_add_months(months) }
+        }
+        _ => {
+            let (_, unit_seconds) = RELATIVE_UNITS.iter().find(|(u, _)| *u == unit)?;
+            now.clone().checked_add_signed(chrono::Duration::try_seconds(count.checked_mul(*unit_seconds)?)?)
+        }
+    }
+}
+
+/// Parses relative time expression with respect to given reference time.
+///
+/// Supported expressions are:
+/// * `now`, `today`, `yesterday` and `tomorrow` (days start at midnight),
+/// * `<N> <unit>(s) ago` and `in <N> <unit>(s)` where unit is one of: second, minute, hour, day, week, month, year,
+/// * `<duration> ago` and `in <duration>` where duration is in format accepted by [parse_duration] (e.g. `1h30m ago`),
+/// * `last <weekday>`, `next <weekday>` and `<weekday>` (same as `last <weekday>`) starting at midnight.
+pub fn parse_relative_time_to<Tz: TimeZone>(val: &str, now: &DateTime<Tz>) -> Result<DateTime<Tz>, ParseRelativeTimeError> {
+    let expr = val.trim().to_lowercase();
+    let words = expr.split_whitespace().collect::<Vec<_>>();
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let unknown = || ParseRelativeTimeError::UnknownExpression(val.to_owned());
+
+    let (count_duration, past) = match words.as_slice() {
+        [] => return Err(ParseRelativeTimeError::Empty),
+        ["now"] => return Ok(now.clone()),
+        ["today"] => return start_of_day(&tz, today),
+        ["yesterday"] => return start_of_day(&tz, today.pred_opt().ok_or(ParseRelativeTimeError::OutOfRange)?),
+        ["tomorrow"] => return start_of_day(&tz, today.succ_opt().ok_or(ParseRelativeTimeError::OutOfRange)?),
+        ["last", weekday] | [weekday] if weekday.parse::<Weekday>().is_ok() => {
+            let weekday = weekday.parse::<Weekday>().unwrap();
+            let days_back = (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday() - 1) % 7 + 1;
+            return start_of_day(&tz, today - chrono::Duration::days(days_back as i64))
+        }
+        ["next", weekday] => {
+            let weekday = weekday.parse::<Weekday>().map_err(|_| unknown())?;
+            let days_forward = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday() - 1) % 7 + 1;
+            return start_of_day(&tz, today + chrono::Duration::days(days_forward as i64))
+        }
+        [rest @ .., "ago"] => (rest, true),
+        ["in", rest @ ..] => (rest, false),
+        _ => return Err(unknown()),
+    };
+
+    let unit_name = |unit: &str| unit.strip_suffix('s').unwrap_or(unit).to_owned();
+    match count_duration {
+        [count, unit] if count.parse::<i64>().is_ok() && RELATIVE_UNITS.iter().any(|(u, _)| *u == unit_name(unit)) => {
+            let count = count.parse::<i64>().unwrap();
+            shift_by_units(now, if past { -count } else { count }, &unit_name(unit)).ok_or(ParseRelativeTimeError::OutOfRange)
+        }
+        [] => Err(unknown()),
+        duration => {
+            let duration = parse_chrono_duration(&duration.join(" ")).map_err(ParseRelativeTimeError::InvalidDuration)?;
+            now.clone().checked_add_signed(if past { -duration } else { duration }).ok_or(ParseRelativeTimeError::OutOfRange)
+        }
+    }
+}
+
+/// Parses relative time expression (e.g. `yesterday`, `2 days ago`, `last monday`) with respect to current local time;
+/// see [parse_relative_time_to].
+///
+/// This is useful with `clap` for arguments like `--since`: `#[arg(value_parser = parse_relative_time)]`
+pub fn parse_relative_time(val: &str) -> Result<DateTime<Local>, ParseRelativeTimeError> {
+    parse_relative_time_to(val, &Local::now())
+}
+
+/// Measures elapsed time of a task and its named phases (laps), logging it when dropped.
+///
+/// Messages are logged at [log::Level::Debug] by default so they show up with `-vv` of [crate::prelude::ArgsLogger];
+/// use [Stopwatch::with_level] to change it.
+/// With [Stopwatch::with_summary] a table of all phases is logged on drop as well (e.g. for a stopwatch spanning whole `main`).
+#[cfg(feature = "log")]
+#[derive(Debug)]
+pub struct Stopwatch {
+    name: String,
+    level: log::Level,
+    summary: bool,
+    start: Instant,
+    lap_start: Instant,
+    laps: Vec<(String, Duration)>,
+    finished: bool,
+}
+
+#[cfg(feature = "log")]
+impl Stopwatch {
+    /// Starts new stopwatch with given name.
+    pub fn start(name: impl Into<String>) -> Stopwatch {
+        let now = Instant::now();
+        Stopwatch {
+            name: name.into(),
+            level: log::Level::Debug,
+            summary: false,
+            start: now,
+            lap_start: now,
+            laps: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Sets log level of the timing messages.
+    pub fn with_level(mut self, level: log::Level) -> Stopwatch {
+        self.level = level;
+        self
+    }
+
+    /// Logs summary table of all laps when the stopwatch is dropped.
+    pub fn with_summary(mut self) -> Stopwatch {
+        self.summary = true;
+        self
+    }
+
+    /// Returns time elapsed since the stopwatch was started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Records phase with given name that took time since previous lap (or start) and returns its duration.
+    pub fn lap(&mut self, name: impl Into<String>) -> Duration {
+        let now = Instant::now();
+        let duration = now - self.lap_start;
+        let name = name.into();
+        log::log!(self.level, "{}: {} took {:.2?}", self.name, name, duration);
+        self.lap_start = now;
+        self.laps.push((name, duration));
+        duration
+    }
+
+    /// Returns recorded laps.
+    pub fn laps(&self) -> &[(String, Duration)] {
+        &self.laps
+    }
+
+    /// Returns table of recorded laps with their share of total elapsed time.
+    pub fn summary(&self) -> String {
+        let total = self.elapsed();
+        let mut rows = self.laps.clone();
+        let rest = total.saturating_sub(self.laps.iter().map(|(_, duration)| *duration).sum());
+        if !self.laps.is_empty() && rest > Duration::ZERO {
+            rows.push(("(other)".to_owned(), rest));
+        }
+        rows.push(("total".to_owned(), total));
+
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(self.name.len());
+        let mut out = format!("{:<width$} {:>12} {:>7}\n", self.name, "time", "share", width = width);
+        for (name, duration) in rows {
+            let share = if total.is_zero() { 100.0 } else { duration.as_secs_f64() / total.as_secs_f64() * 100.0 };
+            out.push_str(&format!("{:<width$} {:>12} {:>6.1}%\n", name, format!("{:.2?}", duration), share, width = width));
+        }
+        out
+    }
+
+    /// Logs summary table of recorded laps.
+    pub fn log_summary(&self) {
+        if log::log_enabled!(self.level) {
+            for line in self.summary().lines() {
+                log::log!(self.level, "{}", line);
+            }
+        }
+    }
+
+    /// Stops the stopwatch logging elapsed time (and summary if enabled) and returns the elapsed time.
+    pub fn finish(mut self) -> Duration {
+        self.report()
+    }
+
+    fn report(&mut self) -> Duration {
+        self.finished = true;
+        let elapsed = self.elapsed();
+        log::log!(self.level, "{} took {:.2?}", self.name, elapsed);
+        if self.summary {
+            self.log_summary();
+        }
+        elapsed
+    }
+}
+
+#[cfg(feature = "log")]
+impl Drop for Stopwatch {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.report();
+        }
+    }
+}
+
+/// Runs given function logging its execution time at [log::Level::Debug].
+#[cfg(feature = "log")]
+pub fn timed<T>(name: impl Into<String>, f: impl FnOnce() -> T) -> T {
+    let _stopwatch = Stopwatch::start(name);
+    f()
 }
 
 /// Sleeps for duration.
@@ -67,6 +983,229 @@ pub fn sleep_sec(seconds: f64) {
     std::thread::sleep(Duration::from_secs_f64(seconds))
 }
 
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Error returned when waiting was interrupted by the interrupt flag being set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupted;
+
+impl Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wait was interrupted")
+    }
+}
+
+impl Error for Interrupted {}
+
+pub(crate) fn sleep_until_interruptible(until: Instant, interrupt: Option<&AtomicBool>) -> Result<(), Interrupted> {
+    loop {
+        if interrupt.map(|flag| flag.load(Ordering::Relaxed)).unwrap_or(false) {
+            return Err(Interrupted)
+        }
+        let remaining = until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(())
+        }
+        std::thread::sleep(if interrupt.is_some() { remaining.min(INTERRUPT_POLL_INTERVAL) } else { remaining });
+    }
+}
+
+/// Sleeps for duration or until interrupt flag is set.
+///
+/// The flag can be registered for termination signals with `signal_flag::register` for all `TERM_SIGNALS`.
+pub fn sleep_interruptible(duration: Duration, interrupt: &AtomicBool) -> Result<(), Interrupted> {
+    sleep_until_interruptible(Instant::now() + duration, Some(interrupt))
+}
+
+/// Fixed-rate ticker that does not drift.
+///
+/// Ticks are scheduled at multiples of the period from the start time regardless of how long the work between ticks takes.
+/// If the work took longer than the period the late tick fires immediately and any further missed ticks are skipped.
+///
+/// Iterating over the interval yields time of each tick until interrupted.
+#[derive(Debug, Clone)]
+pub struct Interval {
+    period: Duration,
+    next: Instant,
+    interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl Interval {
+    /// Creates interval with given period with first tick happening immediately.
+    pub fn new(period: Duration) -> Interval {
+        Interval::starting_at(Instant::now(), period)
+    }
+
+    /// Creates interval with given period with first tick happening at given time.
+    pub fn starting_at(start: Instant, period: Duration) -> Interval {
+        assert!(!period.is_zero(), "interval period must not be zero");
+        Interval {
+            period,
+            next: start,
+            interrupt: None,
+        }
+    }
+
+    /// Stops waiting for the next tick when given flag is set (e.g. by `signal_flag::register`).
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Interval {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Returns period of the interval.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Blocks until the next tick and returns its scheduled time.
+    pub fn tick(&mut self) -> Result<Instant, Interrupted> {
+        sleep_until_interruptible(self.next, self.interrupt.as_deref())?;
+        let tick = self.next;
+
+        let now = Instant::now();
+        self.next += self.period;
+        if self.next <= now {
+            let missed = ((now - self.next).as_nanos() / self.period.as_nanos()) as u32 + 1;
+            self.next += self.period * missed;
+        }
+
+        Ok(tick)
+    }
+}
+
+impl Iterator for Interval {
+    type Item = Instant;
+
+    fn next(&mut self) -> Option<Instant> {
+        self.tick().ok()
+    }
+}
+
+/// Token bucket rate limiter.
+///
+/// Tokens are replenished continuously at given rate up to the burst size (by default equal to one period worth of tokens).
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    tokens_per_sec: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+    interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl RateLimiter {
+    /// Creates rate limiter allowing given number of operations per period (e.g. 10 per second).
+    ///
+    /// The bucket starts full.
+    pub fn new(count: u32, period: Duration) -> RateLimiter {
+        assert!(count > 0 && !period.is_zero(), "rate limiter count and period must not be zero");
+        RateLimiter {
+            tokens_per_sec: count as f64 / period.as_secs_f64(),
+            burst: count as f64,
+            tokens: count as f64,
+            updated: Instant::now(),
+            interrupt: None,
+        }
+    }
+
+    /// Sets maximum number of operations that can be performed in a burst.
+    pub fn with_burst(mut self, burst: u32) -> RateLimiter {
+        assert!(burst > 0, "rate limiter burst must not be zero");
+        self.burst = burst as f64;
+        self.tokens = self.tokens.min(self.burst);
+        self
+    }
+
+    /// Stops waiting for tokens when given flag is set (e.g. by `signal_flag::register`).
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> RateLimiter {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        self.tokens = (self.tokens + (now - self.updated).as_secs_f64() * self.tokens_per_sec).min(self.burst);
+        self.updated = now;
+    }
+
+    /// Takes given number of tokens if available without blocking.
+    pub fn try_acquire_n(&mut self, count: u32) -> bool {
+        self.refill();
+        if self.tokens >= count as f64 {
+            self.tokens -= count as f64;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Takes a token if available without blocking.
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_n(1)
+    }
+
+    /// Blocks until given number of tokens is available and takes them.
+    ///
+    /// Panics if count is greater than the burst size.
+    pub fn acquire_n(&mut self, count: u32) -> Result<(), Interrupted> {
+        assert!(count as f64 <= self.burst, "cannot acquire more tokens than the burst size");
+        loop {
+            if self.try_acquire_n(count) {
+                return Ok(())
+            }
+            let wait = Duration::from_secs_f64((count as f64 - self.tokens) / self.tokens_per_sec);
+            sleep_until_interruptible(Instant::now() + wait, self.interrupt.as_deref())?;
+        }
+    }
+
+    /// Blocks until a token is available and takes it.
+    pub fn acquire(&mut self) -> Result<(), Interrupted> {
+        self.acquire_n(1)
+    }
+}
+
+/// Point in time by which an operation should complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Creates deadline given duration from now.
+    pub fn after(duration: Duration) -> Deadline {
+        Deadline(Instant::now() + duration)
+    }
+
+    /// Creates deadline at given time.
+    pub fn at(instant: Instant) -> Deadline {
+        Deadline(instant)
+    }
+
+    /// Returns time of the deadline.
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Returns time left until the deadline; zero if expired.
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Returns true if deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.0
+    }
+
+    /// Sleeps for given duration but no longer than until the deadline; returns false if the deadline has expired.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        std::thread::sleep(duration.min(self.remaining()));
+        !self.is_expired()
+    }
+
+    /// Sleeps until the deadline or until interrupt flag is set.
+    pub fn wait(&self, interrupt: &AtomicBool) -> Result<(), Interrupted> {
+        sleep_until_interruptible(self.0, Some(interrupt))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,4 +1227,249 @@ mod tests {
 
         assert_eq!(d, chrono::Duration::from_millis_str("1500").unwrap());
     }
+
+    #[test]
+    fn human_duration() {
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration(" 1.5s 2us 3ns").unwrap(), Duration::new(1, 500_002_003));
+        assert_eq!(parse_duration("1w 1d").unwrap(), Duration::from_secs(8 * 24 * 3600));
+        assert_eq!(parse_duration(".5m").unwrap(), Duration::from_secs(30));
+
+        assert_eq!(parse_duration(""), Err(ParseDurationError::Empty));
+        assert_eq!(parse_duration("15"), Err(ParseDurationError::MissingUnit("15".to_owned())));
+        assert_eq!(parse_duration("1y"), Err(ParseDurationError::UnknownUnit("y".to_owned())));
+        assert_eq!(parse_duration("1.2.3s"), Err(ParseDurationError::InvalidNumber("1.2.3".to_owned())));
+        assert_eq!(parse_duration("abc def"), Err(ParseDurationError::InvalidNumber("abc".to_owned())));
+        assert_eq!(parse_duration("1s -2m"), Err(ParseDurationError::InvalidNumber("-2m".to_owned())));
+        assert_eq!(parse_duration("99999999999999999999w"), Err(ParseDurationError::Overflow));
+
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(format_duration(Duration::new(90061, 500_000_000)), "1d1h1m1s500ms");
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(Duration::from_human_str(&Duration::new(3, 7).to_human_string()).unwrap(), Duration::new(3, 7));
+    }
+
+    #[test]
+    fn human_chrono_duration() {
+        assert_eq!(chrono::Duration::from_human_str("-1h30m").unwrap(), chrono::Duration::minutes(-90));
+        assert_eq!(chrono::Duration::minutes(-90).to_human_string(), "-1h30m");
+        assert_eq!(chrono::Duration::from_human_str("2d").unwrap(), chrono::Duration::days(2));
+    }
+
+    #[test]
+    fn system_time_conversions() {
+        let time = UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789);
+        let utc = time.to_utc();
+
+        assert_eq!(utc, Utc.with_ymd_and_hms(2020, 9, 13, 12, 26, 40).unwrap() + chrono::Duration::nanoseconds(123_456_789));
+        assert_eq!(utc.to_system_time(), time);
+        assert_eq!(time.to_local().to_system_time(), time);
+    }
+
+    #[cfg(feature = "filetime")]
+    #[test]
+    fn file_time_conversions() {
+        for time in [UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789), UNIX_EPOCH - Duration::new(1_000, 250)] {
+            let file_time = time.to_file_time();
+            assert_eq!(file_time.to_system_time(), time);
+            assert_eq!(file_time.to_utc(), time.to_utc());
+            assert_eq!(time.to_utc().to_file_time(), file_time);
+        }
+    }
+
+    #[test]
+    fn metadata_time() {
+        let metadata = std::fs::metadata(file!()).unwrap();
+        assert_eq!(metadata.modified_utc().unwrap().to_system_time(), metadata.modified().unwrap());
+    }
+
+    #[test]
+    fn relative_format() {
+        let now = Utc.with_ymd_and_hms(2020, 10, 9, 12, 0, 0).unwrap();
+
+        assert_eq!(format_relative_to(&now, &now, 1), "just now");
+        assert_eq!(format_relative_to(&(now - chrono::Duration::minutes(3)), &now, 1), "3 minutes ago");
+        assert_eq!(format_relative_to(&(now + chrono::Duration::days(2)), &now, 1), "in 2 days");
+        assert_eq!(format_relative_to(&(now - chrono::Duration::minutes(65)), &now, 2), "1 hour 5 minutes ago");
+        assert_eq!(format_relative_to(&(now - chrono::Duration::seconds(3605)), &now, 2), "1 hour ago");
+        assert_eq!(format_relative_to(&(now - chrono::Duration::seconds(3605)), &now, 3), "1 hour 5 seconds ago");
+    }
+
+    #[test]
+    fn relative_parse() {
+        // Friday
+        let now = Utc.with_ymd_and_hms(2020, 10, 9, 12, 30, 0).unwrap();
+        let midnight = |day| Utc.with_ymd_and_hms(2020, 10, day, 0, 0, 0).unwrap();
+
+        assert_eq!(parse_relative_time_to("now", &now).unwrap(), now);
+        assert_eq!(parse_relative_time_to("Yesterday", &now).unwrap(), midnight(8));
+        assert_eq!(parse_relative_time_to("tomorrow", &now).unwrap(), midnight(10));
+        assert_eq!(parse_relative_time_to("2 days ago", &now).unwrap(), now - chrono::Duration::days(2));
+        assert_eq!(parse_relative_time_to("in 1 week", &now).unwrap(), now + chrono::Duration::weeks(1));
+        assert_eq!(parse_relative_time_to("3 months ago", &now).unwrap(), Utc.with_ymd_and_hms(2020, 7, 9, 12, 30, 0).unwrap());
+        assert_eq!(parse_relative_time_to("1h30m ago", &now).unwrap(), now - chrono::Duration::minutes(90));
+        assert_eq!(parse_relative_time_to("last monday", &now).unwrap(), midnight(5));
+        assert_eq!(parse_relative_time_to("last friday", &now).unwrap(), midnight(2));
+        assert_eq!(parse_relative_time_to("next friday", &now).unwrap(), midnight(16));
+        assert_eq!(parse_relative_time_to("sat", &now).unwrap(), midnight(3));
+
+        assert_eq!(parse_relative_time_to("", &now), Err(ParseRelativeTimeError::Empty));
+        assert_eq!(parse_relative_time_to("the other day", &now), Err(ParseRelativeTimeError::UnknownExpression("the other day".to_owned())));
+        assert!(matches!(parse_relative_time_to("2 fortnights ago", &now), Err(ParseRelativeTimeError::InvalidDuration(_))));
+    }
+
+    #[test]
+    fn timestamp_parse() {
+        let time = Utc.with_ymd_and_hms(2020, 10, 9, 12, 30, 0).unwrap();
+        let midnight = Utc.with_ymd_and_hms(2020, 10, 9, 0, 0, 0).unwrap();
+        let cest = FixedOffset::east_opt(2 * 3600).unwrap();
+
+        assert_eq!(parse_timestamp_utc("2020-10-09T14:30:00+02:00").unwrap(), time);
+        assert_eq!(parse_timestamp_utc("2020-10-09 12:30:00.5Z").unwrap(), time + chrono::Duration::milliseconds(500));
+        assert_eq!(parse_timestamp_utc("2020-10-09T12:30Z").unwrap(), time);
+        assert_eq!(parse_timestamp_utc("2020-10-09 12:30:00,123").unwrap(), time + chrono::Duration::milliseconds(123));
+        assert_eq!(parse_timestamp_utc("2020-10-09 14:30:00,5 +0200").unwrap(), time + chrono::Duration::milliseconds(500));
+        assert_eq!(parse_timestamp_utc("2020-10-09 12:30:00").unwrap(), time);
+        assert_eq!(parse_timestamp_in("2020-10-09 14:30", &cest).unwrap(), time);
+        assert_eq!(parse_timestamp_utc("20201009T123000").unwrap(), time);
+        assert_eq!(parse_timestamp_utc("2020-10-09").unwrap(), midnight);
+        assert_eq!(parse_timestamp_in("2020-10-09", &cest).unwrap(), midnight - chrono::Duration::hours(2));
+        assert_eq!(parse_timestamp_utc("20201009").unwrap(), midnight);
+        assert_eq!(parse_timestamp_utc("2020-W41-5").unwrap(), midnight);
+        assert_eq!(parse_timestamp_utc("2020-283").unwrap(), midnight);
+        assert_eq!(parse_timestamp_utc("2020-10").unwrap(), Utc.with_ymd_and_hms(2020, 10, 1, 0, 0, 0).unwrap());
+        assert_eq!(parse_timestamp_utc("2020").unwrap(), Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(parse_timestamp_utc("1602246600").unwrap(), time);
+        assert_eq!(parse_timestamp_utc("@1602246600123").unwrap(), time + chrono::Duration::milliseconds(123));
+        assert_eq!(parse_timestamp_utc("@12345678").unwrap(), Utc.timestamp_opt(12345678, 0).unwrap());
+        assert_eq!(parse_timestamp_utc("@2020").unwrap(), Utc.timestamp_opt(2020, 0).unwrap());
+        assert_eq!(parse_timestamp_utc("@2020-10-09"), Err(ParseTimestampError::UnknownFormat("@2020-10-09".to_owned())));
+        assert_eq!(parse_timestamp_utc("Fri, 09 Oct 2020 12:30:00 +0000").unwrap(), time);
+        assert_eq!(parse_timestamp_utc("09/Oct/2020:14:30:00 +0200").unwrap(), time);
+        assert_eq!(parse_timestamp_utc("Oct  9 12:30:00").unwrap().with_year(2020).unwrap(), time);
+
+        assert_eq!(parse_timestamp_utc(" "), Err(ParseTimestampError::Empty));
+        assert_eq!(parse_timestamp_utc("2020-13-01"), Err(ParseTimestampError::UnknownFormat("2020-13-01".to_owned())));
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn stopwatch() {
+        let mut stopwatch = Stopwatch::start("job").with_level(log::Level::Info);
+        sleep(Duration::from_millis(10));
+        assert!(stopwatch.lap("load") >= Duration::from_millis(10));
+        stopwatch.lap("process");
+        assert_eq!(stopwatch.laps().iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["load", "process"]);
+
+        let summary = stopwatch.summary();
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("job"));
+        assert!(lines[1].starts_with("load"));
+        assert!(lines.last().unwrap().starts_with("total"));
+        assert!(stopwatch.finish() >= Duration::from_millis(10));
+
+        assert_eq!(timed("answer", || 42), 42);
+    }
+
+    #[test]
+    fn interval() {
+        let start = Instant::now();
+        let period = Duration::from_millis(100);
+        let mut interval = Interval::starting_at(start, period);
+        let aligned = |tick: Instant| start + period * ((tick - start).as_nanos() / period.as_nanos()) as u32 == tick;
+
+        assert_eq!(interval.tick().unwrap(), start);
+        let second = interval.tick().unwrap();
+        assert!(second > start && aligned(second) && second <= Instant::now());
+        sleep(Duration::from_millis(250));
+        // Late tick fires immediately, then missed ticks are skipped but schedule stays aligned to start
+        let before = Instant::now();
+        let late = interval.tick().unwrap();
+        assert!(late > second && aligned(late) && late <= before);
+        let next = interval.tick().unwrap();
+        assert!(next > before && aligned(next) && next >= late + period * 2);
+
+        let interrupt = Arc::new(AtomicBool::new(true));
+        assert_eq!(interval.with_interrupt(interrupt).next(), None);
+    }
+
+    #[test]
+    fn rate_limiter() {
+        let created = Instant::now();
+        let mut limiter = RateLimiter::new(10, Duration::from_secs(1)).with_burst(2);
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        // Third token is replenished 100ms after the bucket was created full
+        limiter.acquire().unwrap();
+        assert!(created.elapsed() >= Duration::from_millis(99));
+
+        let interrupt = Arc::new(AtomicBool::new(true));
+        let mut limiter = limiter.with_interrupt(interrupt);
+        assert_eq!(limiter.acquire_n(2), Err(Interrupted));
+    }
+
+    #[test]
+    fn deadline() {
+        let deadline = Deadline::after(Duration::from_millis(20));
+        assert!(!deadline.is_expired());
+        assert!(deadline.remaining() <= Duration::from_millis(20));
+        assert!(!deadline.sleep(Duration::from_secs(10)));
+        assert!(deadline.is_expired());
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+
+        let interrupt = AtomicBool::new(true);
+        assert_eq!(Deadline::after(Duration::from_secs(10)).wait(&interrupt), Err(Interrupted));
+        assert_eq!(sleep_interruptible(Duration::from_secs(10), &interrupt), Err(Interrupted));
+    }
+
+    #[test]
+    fn date_range() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        assert_eq!(DateRange::new(date(2020, 10, 30), date(2020, 11, 2)).collect::<Vec<_>>(), [date(2020, 10, 30), date(2020, 10, 31), date(2020, 11, 1)]);
+        assert_eq!(DateRange::inclusive(date(2020, 10, 30), date(2020, 11, 2)).count(), 4);
+        assert_eq!(DateRange::new(date(2020, 10, 30), date(2020, 10, 30)).count(), 0);
+        assert_eq!(DateRange::inclusive(date(2020, 10, 1), date(2020, 10, 31)).with_step(DateStep::Weeks(2)).collect::<Vec<_>>(), [date(2020, 10, 1), date(2020, 10, 15), date(2020, 10, 29)]);
+        assert_eq!(DateRange::inclusive(date(2020, 1, 31), date(2020, 4, 30)).with_step(DateStep::Months(1)).collect::<Vec<_>>(), [date(2020, 1, 31), date(2020, 2, 29), date(2020, 3, 31), date(2020, 4, 30)]);
+        assert_eq!(DateRange::new(date(2020, 2, 29), date(2025, 1, 1)).with_step(DateStep::Years(2)).collect::<Vec<_>>(), [date(2020, 2, 29), date(2022, 2, 28), date(2024, 2, 29)]);
+
+        assert_eq!(date(2020, 10, 9).start_of_iso_week(), date(2020, 10, 5));
+        assert_eq!(date(2020, 10, 9).end_of_iso_week(), date(2020, 10, 11));
+        assert_eq!(date(2020, 2, 9).end_of_month(), date(2020, 2, 29));
+        assert_eq!(DateRange::iso_week(2020, 53).unwrap().collect::<Vec<_>>().len(), 7);
+        assert_eq!(DateRange::iso_week(2020, 53).unwrap().last_date(), Some(date(2021, 1, 3)));
+        assert_eq!(DateRange::month(2021, 2).unwrap().last_date(), Some(date(2021, 2, 28)));
+
+        let weeks = DateRange::inclusive(date(2020, 10, 9), date(2020, 10, 20)).iso_weeks().map(|week| (week.first_date(), week.last_date().unwrap())).collect::<Vec<_>>();
+        assert_eq!(weeks, [(date(2020, 10, 9), date(2020, 10, 11)), (date(2020, 10, 12), date(2020, 10, 18)), (date(2020, 10, 19), date(2020, 10, 20))]);
+        let months = DateRange::new(date(2020, 10, 9), date(2020, 12, 1)).months().map(|month| (month.first_date(), month.last_date().unwrap())).collect::<Vec<_>>();
+        assert_eq!(months, [(date(2020, 10, 9), date(2020, 10, 31)), (date(2020, 11, 1), date(2020, 11, 30))]);
+
+        assert_eq!(parse_date("2020-10-09").unwrap(), date(2020, 10, 9));
+        assert_eq!(parse_date("20201009").unwrap(), date(2020, 10, 9));
+        assert_eq!(parse_date("today").unwrap(), Local::now().date_naive());
+        assert!(parse_date("someday").is_err());
+    }
+
+    #[cfg(feature = "clap")]
+    #[test]
+    fn args_date_range() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            dates: ArgsDateRange,
+        }
+
+        let range = Cli::parse_from(["test", "--from", "2020-10-01", "--to", "20201003"]).dates.range().unwrap();
+        assert_eq!(range.count(), 3);
+        let from = NaiveDate::from_ymd_opt(2020, 10, 3).unwrap();
+        let to = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        assert_eq!(Cli::parse_from(["test", "--from", "2020-10-03", "--to", "2020-10-01"]).dates.range(), Err(DateRangeError::Reversed { from, to }));
+        assert_eq!(Cli::parse_from(["test"]).dates.range().unwrap().count(), 1);
+    }
 }